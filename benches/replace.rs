@@ -0,0 +1,59 @@
+//! Compares the literal automaton of the replacer with its alternation regex over a large synthetic project
+//!
+//! Run with `cargo bench --bench replace`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use cuttercookie::tool::{RegexReplacer, SubstitutionRule};
+
+/// Values of the synthetic variables, sharing prefixes like real project names
+fn values(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("acme_{}_service{}", ["billing", "auth", "search", "mail"][i % 4], i)).collect()
+}
+
+/// Builds about 4 MB of source-like text where one line in eight holds a value
+fn corpus(values: &[String]) -> String {
+    let mut corpus = String::new();
+    let mut seed = 42u64;
+    while corpus.len() < 4 << 20 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let line = match seed >> 61 {
+            0 => format!("    let client = {}::Client::new(config);\n", values[(seed >> 16) as usize % values.len()]),
+            _ => "    let accumulated = items.iter().map(|item| item.weight * factor).sum::<f64>();\n".to_string()
+        };
+        corpus.push_str(&line);
+    }
+    corpus
+}
+
+fn bench_replace(c: &mut Criterion) {
+    let mut group = c.benchmark_group("replace");
+    group.sample_size(10);
+    for count in [10, 100, 500] {
+        let values = values(count);
+        let corpus = corpus(&values);
+        group.throughput(Throughput::Bytes(corpus.len() as u64));
+
+        // Plain values are matched by the literal automaton, while wrapping them in a group
+        // sends the same escaped values through the single alternation regex of the rules
+        let replacer = |pattern: fn(&str) -> String| RegexReplacer::new(values.iter()
+            .enumerate()
+            .map(|(i, value)| SubstitutionRule::new(pattern(value), format!("var_{}", i)))
+            .collect());
+        let (literals, alternation) = (replacer(str::to_string), replacer(|value| format!("(?:{})", regex::escape(value))));
+        assert_eq!(literals.replace(&corpus), alternation.replace(&corpus));
+
+        group.bench_with_input(BenchmarkId::new("replacer", count), &corpus, |b, corpus| {
+            b.iter(|| literals.replace(black_box(corpus)))
+        });
+        group.bench_with_input(BenchmarkId::new("alternation", count), &corpus, |b, corpus| {
+            b.iter(|| alternation.replace(black_box(corpus)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_replace);
+criterion_main!(benches);
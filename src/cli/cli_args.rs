@@ -1,12 +1,112 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+
+use crate::tool::SecretPolicy;
 
 #[derive(Parser)]
 #[command(name = "cuttercookie")]
 #[command(about = "File system utility tools", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
+    /// Optional analysis command, templatizes `path` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Directory path to start from
+    #[arg(required = true)]
+    pub path: Option<String>,
+
+    /// Directory that will not be included
+    #[arg(long, short, value_delimiter = ',')]
+    pub excluded_items: Vec<String>,
+
+    /// flag that exclude the main project directory
+    #[arg(long, short)]
+    pub no_root: bool,
+
+    /// Remove the `cuttercookie:ignore-*` directive comments from the template
+    #[arg(long)]
+    pub strip_directives: bool,
+
+    /// Number of threads processing the files, every core when 0
+    #[arg(long, short, default_value_t = 0)]
+    pub jobs: usize,
+
+    /// List the pairs of rules whose matches overlap, which scans the files once more per rule
+    #[arg(long)]
+    pub report_overlaps: bool
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Propose a cookiecutter.json by analysing the source project
+    Suggest(SuggestArgs),
+
+    /// Infer variables by diffing two instances of a project, then templatize the first one
+    Infer(InferArgs),
+
+    /// Templatize a project from two variants, with and without an optional feature
+    Variants(VariantsArgs),
+}
+
+#[derive(Args)]
+pub struct SuggestArgs {
+    /// Directory path of the project to analyse
     pub path: String,
 
+    /// Directory that will not be scanned
+    #[arg(long, short, value_delimiter = ',')]
+    pub excluded_items: Vec<String>,
+
+    /// Maximum number of identifier tokens proposed as variables
+    #[arg(long, default_value_t = 5)]
+    pub max_tokens: usize
+}
+
+#[derive(Args)]
+pub struct InferArgs {
+    /// Directory path of the instance to templatize, its values become the defaults
+    pub first: String,
+
+    /// Directory path of another instance generated from the same template
+    pub second: String,
+
+    /// Directory that will not be included
+    #[arg(long, short, value_delimiter = ',')]
+    pub excluded_items: Vec<String>,
+
+    /// flag that exclude the main project directory
+    #[arg(long, short)]
+    pub no_root: bool,
+
+    /// Minimum number of differences showing a pair of values for it to become a variable
+    #[arg(long, default_value_t = 1)]
+    pub min_occurrences: usize,
+
+    /// What happens to the secrets found: `fail`, `exclude` or `variable`
+    #[arg(long, default_value = "fail")]
+    pub secrets: SecretPolicy,
+
+    /// Number of threads processing the files, every core when 0
+    #[arg(long, short, default_value_t = 0)]
+    pub jobs: usize,
+
+    /// List the pairs of rules whose matches overlap, which scans the files once more per rule
+    #[arg(long)]
+    pub report_overlaps: bool
+}
+
+#[derive(Args)]
+pub struct VariantsArgs {
+    /// Directory path of the variant with the optional feature
+    pub with: String,
+
+    /// Directory path of the variant without the optional feature
+    pub without: String,
+
+    /// Name of the boolean variable enabling the feature
+    #[arg(long, short)]
+    pub flag: String,
+
     /// Directory that will not be included
     #[arg(long, short, value_delimiter = ',')]
     pub excluded_items: Vec<String>,
@@ -25,7 +125,7 @@ mod tests {
     #[test]
     fn test_basic_path_parsing() {
         let args = Cli::parse_from(["cuttercookie", "/test/path"]);
-        assert_eq!(args.path, "/test/path");
+        assert_eq!(args.path.as_deref(), Some("/test/path"));
         assert!(args.excluded_items.is_empty());
     }
 
@@ -39,7 +139,7 @@ mod tests {
             "node_modules,target,.git"
         ]);
 
-        assert_eq!(args.path, "/test/path");
+        assert_eq!(args.path.as_deref(), Some("/test/path"));
         assert_eq!(
             args.excluded_items,
             vec!["node_modules", "target", ".git"]
@@ -56,7 +156,7 @@ mod tests {
             "node_modules,target,.git"
         ]);
 
-        assert_eq!(args.path, "/test/path");
+        assert_eq!(args.path.as_deref(), Some("/test/path"));
         assert_eq!(
             args.excluded_items,
             vec!["node_modules", "target", ".git"]
@@ -87,15 +187,46 @@ mod tests {
             ""
         ]);
 
-        assert_eq!(args.path, "/test/path");
+        assert_eq!(args.path.as_deref(), Some("/test/path"));
         assert!(args.excluded_items.is_empty());
     }
 
+    /// Tests parsing of the number of threads
+    #[test]
+    fn test_jobs() {
+        let args = Cli::parse_from(["cuttercookie", "/test/path"]);
+        assert_eq!(args.jobs, 0);
+
+        let args = Cli::parse_from(["cuttercookie", "/test/path", "-j", "4"]);
+        assert_eq!(args.jobs, 4);
+    }
+
+    /// Tests that the overlap diagnostic is only enabled on demand
+    #[test]
+    fn test_report_overlaps() {
+        assert!(!Cli::parse_from(["cuttercookie", "/test/path"]).report_overlaps);
+        assert!(Cli::parse_from(["cuttercookie", "/test/path", "--report-overlaps"]).report_overlaps);
+    }
+
+    /// Tests parsing of the secret policy of the infer subcommand
+    #[test]
+    fn test_infer_secret_policy() {
+        let args = Cli::parse_from(["cuttercookie", "infer", "a", "b"]);
+        let Some(Command::Infer(infer_args)) = args.command else { panic!("Expected infer") };
+        assert_eq!(infer_args.secrets, SecretPolicy::Fail);
+
+        let args = Cli::parse_from(["cuttercookie", "infer", "a", "b", "--secrets", "variable"]);
+        let Some(Command::Infer(infer_args)) = args.command else { panic!("Expected infer") };
+        assert_eq!(infer_args.secrets, SecretPolicy::Variable);
+
+        assert!(Cli::try_parse_from(["cuttercookie", "infer", "a", "b", "--secrets", "ignore"]).is_err());
+    }
+
     /// Tests handling of spaces in path argument
     #[test]
     fn test_path_with_spaces() {
         let args = Cli::parse_from(["cuttercookie", "/test/path with spaces"]);
-        assert_eq!(args.path, "/test/path with spaces");
+        assert_eq!(args.path.as_deref(), Some("/test/path with spaces"));
     }
 
     /// Tests handling of spaces in excluded items
@@ -108,10 +239,83 @@ mod tests {
             "node modules,target files,.git data"
         ]);
 
-        assert_eq!(args.path, "/test/path");
+        assert_eq!(args.path.as_deref(), Some("/test/path"));
         assert_eq!(
             args.excluded_items,
             vec!["node modules", "target files", ".git data"]
         );
     }
+
+    /// Tests parsing of the suggest subcommand and its options
+    #[test]
+    fn test_suggest_subcommand() {
+        let args = Cli::parse_from([
+            "cuttercookie",
+            "suggest",
+            "/test/path",
+            "-e",
+            "target",
+            "--max-tokens",
+            "3"
+        ]);
+
+        assert!(args.path.is_none());
+        match args.command {
+            Some(Command::Suggest(suggest)) => {
+                assert_eq!(suggest.path, "/test/path");
+                assert_eq!(suggest.excluded_items, vec!["target"]);
+                assert_eq!(suggest.max_tokens, 3);
+            },
+            _ => panic!("Expected the suggest subcommand")
+        }
+    }
+
+    /// Tests parsing of the infer subcommand and its two instances
+    #[test]
+    fn test_infer_subcommand() {
+        let args = Cli::parse_from([
+            "cuttercookie",
+            "infer",
+            "/first",
+            "/second",
+            "--no-root",
+            "--min-occurrences",
+            "2"
+        ]);
+
+        match args.command {
+            Some(Command::Infer(infer)) => {
+                assert_eq!(infer.first, "/first");
+                assert_eq!(infer.second, "/second");
+                assert!(infer.no_root);
+                assert_eq!(infer.min_occurrences, 2);
+            },
+            _ => panic!("Expected the infer subcommand")
+        }
+    }
+
+    /// Tests that the variants subcommand requires the flag name
+    #[test]
+    fn test_variants_subcommand() {
+        let args = Cli::parse_from([
+            "cuttercookie",
+            "variants",
+            "/with",
+            "/without",
+            "--flag",
+            "use_docker"
+        ]);
+
+        match args.command {
+            Some(Command::Variants(variants)) => {
+                assert_eq!(variants.with, "/with");
+                assert_eq!(variants.without, "/without");
+                assert_eq!(variants.flag, "use_docker");
+            },
+            _ => panic!("Expected the variants subcommand")
+        }
+
+        let result = Cli::try_parse_from(["cuttercookie", "variants", "/with", "/without"]);
+        assert!(matches!(result, Err(err) if err.kind() == ErrorKind::MissingRequiredArgument));
+    }
 }
\ No newline at end of file
@@ -1,30 +1,244 @@
 pub mod cli_args;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 
 use clap::Parser;
 
-use cli_args::Cli;
-use crate::tool::{build_replacer, process_files};
+use cli_args::{Cli, Command, InferArgs, SuggestArgs, VariantsArgs};
+use crate::tool::{
+    build_inferred_replacer, build_replacer, find_guids, find_project_license, guid_rules, infer_variables,
+    load_config, package_path_rules, process_files, process_variants, render_inferred, render_occurrences, render_suggestion,
+    resolve_conditional_paths, resolve_package_paths, suggest_variables, write_post_gen_hook,
+    CookiecutterConfig, CuttercookieConfig, DateTemplater, EncodingDetector, GuidVariable, LicenseTemplater, ProcessOptionsBuilder, ProcessReport,
+    RegexReplacer, RuleOptions, SecretScanner, StructuredTemplater, LICENSE_VARIABLE, TIME_EXTENSION
+};
+
+/// Name of the cookiecutter configuration, read from and written to the current directory
+const COOKIECUTTER_JSON: &str = "cookiecutter.json";
+
+/// Name of the optional cuttercookie configuration, read from the current directory
+const CUTTERCOOKIE_JSON: &str = "cuttercookie.json";
 
 /// Application entry point that handles command-line arguments and file processing
 ///
 /// # Functionality
 /// - Parses command-line arguments
-/// - Validates working directory state
-/// - Configures and executes file processing based on provided arguments
-///
-/// # Process Flow
-/// 1. Parses command-line arguments using `clap`
-/// 2. Constructs path to `cuttercookie.json` configuration file
-/// 3. Initializes regex replacer with patterns from configuration
-/// 4. Validates that current directory is empty
-/// 5. Processes files according to specified parameters
+/// - Dispatches to the requested subcommand, or templatizes the given path
 ///
 /// # Returns
 /// * `Result<(), String>` - Success (`Ok(())`) if processing completes,
 ///   or `Err` with error description if any step fails
+pub fn entry_point() -> Result<(), String> {
+    // parse the console args
+    let args = Cli::parse();
+
+    match args.command {
+        Some(Command::Suggest(suggest_args)) => suggest(suggest_args),
+        Some(Command::Infer(infer_args)) => infer(infer_args),
+        Some(Command::Variants(variants_args)) => variants(variants_args),
+        None => templatize(args)
+    }
+}
+
+/// Prints a proposed cookiecutter.json for the analysed project
+///
+/// The JSON alone goes to the standard output, so that it can be redirected to a file,
+/// and the occurrence counts to the standard error.
+///
+/// # Returns
+/// * `Result<(), String>` - Success, or the error met while analysing the project
+fn suggest(args: SuggestArgs) -> Result<(), String> {
+    let mut warnings = Vec::new();
+    let candidates = suggest_variables(&args.path, &args.excluded_items, args.max_tokens, &mut warnings)
+        .map_err(|err| err.to_string())?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let rendered = render_suggestion(&candidates)
+        .map_err(|err| err.to_string())?;
+    println!("{}", rendered);
+    eprint!("{}", render_occurrences(&candidates));
+    Ok(())
+}
+
+/// Checks that the current directory only contains the allowed items
+///
+/// # Errors
+/// * Returns error if the current directory holds any other item
+fn ensure_empty_current_dir(allowed_items: &[&str]) -> Result<(), String> {
+    let entries = fs::read_dir(".")
+        .expect("Failed to read current directory")
+        .filter_map(|e| e.ok())
+        .filter(|f| !allowed_items.iter().any(|item| f.file_name() == *item))
+        .count();
+
+    if entries > 0 {
+        return Err("Current directory is not empty. Please run in an empty directory".to_string());
+    }
+    Ok(())
+}
+
+/// Prints the findings of a run: the secrets replaced or left out of the template,
+/// the warnings, and the rules whose matches overlap
+fn print_report(report: &ProcessReport) {
+    if !report.secrets.is_empty() {
+        println!("Secrets found:");
+        for finding in &report.secrets {
+            match finding.variable {
+                Some(_) => println!("  {}", finding),
+                None => println!("  {}, file excluded", finding)
+            }
+        }
+    }
+    if !report.warnings.is_empty() {
+        println!("Warnings:");
+        for warning in &report.warnings {
+            println!("  {}", warning);
+        }
+    }
+    if !report.overlaps.is_empty() {
+        println!("Overlapping rules, only one applies where their matches overlap:");
+        for ((a, b), path) in &report.overlaps {
+            println!("  {} and {}, first in {}", a, b, path);
+        }
+    }
+}
+
+/// Infers variables from two instances of a project and templatizes the first one
+///
+/// The inferred variables are printed with both values, then written with the
+/// first instance values as defaults to the `cookiecutter.json` of the current directory.
+///
+/// # Errors
+/// Returns error in following cases:
+/// - Current directory is not empty
+/// - Either instance cannot be read
+/// - File processing encounters errors
+fn infer(args: InferArgs) -> Result<(), String> {
+    ensure_empty_current_dir(&[])?;
+
+    let variables = infer_variables(&args.first, &args.second, &args.excluded_items, args.min_occurrences)
+        .map_err(|err| err.to_string())?;
+    println!("{}", render_inferred(&variables));
+
+    let dest_path = PathBuf::new();
+    let options = ProcessOptionsBuilder::default()
+        .secrets(Some(SecretScanner::new(args.secrets)))
+        .jobs(args.jobs)
+        .report_overlaps(args.report_overlaps)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let report = process_files(
+        args.first.as_str(),
+        &dest_path.display().to_string(),
+        args.excluded_items,
+        build_inferred_replacer(&variables),
+        !args.no_root,
+        options)
+        .map_err(|err| format!("Proccess had error : {}", err))?;
+    print_report(&report);
+
+    let mut config = CookiecutterConfig::new();
+    for variable in &variables {
+        config.insert(&variable.variable, serde_json::Value::String(variable.first.clone()));
+    }
+    for finding in &report.secrets {
+        if let Some(variable) = &finding.variable {
+            config.insert_default(variable, serde_json::Value::String(String::new()));
+        }
+    }
+    for variable in &report.variables {
+        config.insert_default(variable, serde_json::Value::Bool(true));
+    }
+    config.save(&dest_path.join(COOKIECUTTER_JSON).to_string_lossy())
+        .map_err(|err| err.to_string())
+}
+
+/// Applies the rule options of `cuttercookie.json` to the rules of a replacer
+///
+/// Rules may be restricted to some files, or to paths or contents, and to boundaries.
+///
+/// # Errors
+/// * Returns error if a rule option is invalid or names an unknown variable
+fn with_rule_options(replacer: RegexReplacer, config: &CuttercookieConfig) -> Result<RegexReplacer, String> {
+    let rule_options = config.rules.iter()
+        .map(|(variable, rule_config)| Ok((variable.clone(), rule_config.options(config.boundary, config.normalize)?)))
+        .collect::<Result<_, String>>()?;
+    let defaults = RuleOptions { boundary: config.boundary, normalize: config.normalize, ..RuleOptions::default() };
+    replacer.with_options(rule_options, defaults)
+}
+
+/// Templatizes two variants of a project into the current directory
+///
+/// Differing contents become `{% if %}` blocks on the flag, paths only present in one
+/// variant are removed by a generated post generation hook, and the flag is added to
+/// the `cookiecutter.json` of the current directory as a boolean defaulting to `true`.
+///
+/// # Errors
+/// Returns error in following cases:
+/// - Current directory is not empty
+/// - Configuration file cannot be read or parsed
+/// - File processing encounters errors
+fn variants(args: VariantsArgs) -> Result<(), String> {
+    let dest_path = PathBuf::new();
+    let json_path = dest_path
+        .join(COOKIECUTTER_JSON)
+        .to_string_lossy()
+        .into_owned();
+    let replacer = build_replacer(&json_path)
+        .map_err(|err| err.to_string())?;
+    let config = load_config(&dest_path.join(CUTTERCOOKIE_JSON).to_string_lossy())
+        .map_err(|err| err.to_string())?;
+    let replacer = with_rule_options(replacer, &config)?;
+    ensure_empty_current_dir(&[COOKIECUTTER_JSON, CUTTERCOOKIE_JSON])?;
+
+    let options = ProcessOptionsBuilder::default()
+        .secrets(Some(SecretScanner::new(config.secrets.policy)))
+        .encodings(EncodingDetector::new(&config.encodings)?)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let (conditional_paths, report) = process_variants(
+        args.with.as_str(),
+        args.without.as_str(),
+        &dest_path.display().to_string(),
+        args.excluded_items,
+        replacer,
+        &args.flag,
+        !args.no_root,
+        &options)
+        .map_err(|err| format!("Proccess had error : {}", err))?;
+    print_report(&report);
+
+    if !conditional_paths.is_empty() {
+        write_post_gen_hook(&dest_path, &conditional_paths)?;
+    }
+
+    let mut config = CookiecutterConfig::load(&json_path)
+        .map_err(|err| err.to_string())?;
+    config.insert_default(&args.flag, serde_json::Value::Bool(true));
+
+    // Replaced secrets are prompted for, empty by default
+    for finding in &report.secrets {
+        if let Some(variable) = &finding.variable {
+            config.insert_default(variable, serde_json::Value::String(String::new()));
+        }
+    }
+    config.save(&json_path)
+        .map_err(|err| err.to_string())
+}
+
+/// Templatizes the project at `args.path` into the current directory
+///
+/// # Process Flow
+/// 1. Constructs paths to `cookiecutter.json` and `cuttercookie.json` configuration files
+/// 2. Initializes regex replacer with patterns from configuration
+/// 3. Applies the options of `cuttercookie.json`: package paths, GUIDs, dates, license
+///    and conditional paths
+/// 4. Validates that current directory is empty
+/// 5. Processes files according to specified parameters
+/// 6. Writes the post generation hook removing the conditional paths
+/// 7. Saves the variables and extensions added by the options to `cookiecutter.json`
 ///
 /// # Errors
 /// Returns error in following cases:
@@ -32,41 +246,140 @@ use crate::tool::{build_replacer, process_files};
 /// - Configuration file cannot be read or parsed
 /// - File processing encounters errors
 /// - Directory access is restricted
-pub fn entry_point() -> Result<(), String> {
-    // parse the console args
-    let args = Cli::parse();
+fn templatize(args: Cli) -> Result<(), String> {
+    let path = args.path.ok_or("Missing path of the project to templatize")?;
 
-    // Build the path of cuttercookie.json
+    // Build the paths of cookiecutter.json and cuttercookie.json
     let dest_path = PathBuf::new();
     let json_path = dest_path
-        .join("cookiecutter.json")
+        .join(COOKIECUTTER_JSON)
+        .to_string_lossy()
+        .into_owned();
+    let config_path = dest_path
+        .join(CUTTERCOOKIE_JSON)
         .to_string_lossy()
         .into_owned();
 
     // Build the replacer
     let replacer = build_replacer(&json_path)
         .map_err(|err| err.to_string())?;
+    let config = load_config(&config_path)
+        .map_err(|err| err.to_string())?;
 
-    // Check if current dir is empty
-    let entries = fs::read_dir(".")
-        .expect("Failed to read current directory")
-        .filter_map(|e| e.ok())
-        .filter(|f| f.file_name() != "cookiecutter.json")
-        .count();
+    let replacer = with_rule_options(replacer, &config)?;
 
-    if entries > 0 {
-        return Err("Current directory is not empty. Please run in an empty directory".to_string());
+    // Variables added by the options are saved once the project is templatized
+    let mut cookiecutter = CookiecutterConfig::load(&json_path)
+        .map_err(|err| err.to_string())?;
+    let original_cookiecutter = cookiecutter.clone();
+
+    // Package paths are matched before the dotted package rules
+    let package_paths = resolve_package_paths(&cookiecutter, &config.package_paths)?;
+    let mut replacer = replacer.with_rules(package_path_rules(&package_paths));
+    for package in &package_paths {
+        cookiecutter.insert(&package.path_variable(), serde_json::Value::String(package.path_expression()));
+    }
+
+    // GUIDs are replaced by values generated for every project
+    if let Some(guid_config) = &config.guids {
+        let guids = find_guids(&path, &args.excluded_items, &guid_config.include)
+            .map_err(|err| err.to_string())?;
+        replacer = replacer.with_rules(guid_rules(&guids));
+        for guid in &guids {
+            cookiecutter.insert(&guid.variable, serde_json::Value::String(GuidVariable::default_expression()));
+        }
+    }
+
+    // Dates are rendered by the time extension
+    let dates = config.dates.as_ref()
+        .map(|date_config| DateTemplater::new(&date_config.include))
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    if dates.is_some() {
+        cookiecutter.add_extension(TIME_EXTENSION);
+    }
+
+    // The license becomes a choice between the project one and bundled ones
+    let license = match &config.licenses {
+        Some(license_config) => {
+            let project_license = find_project_license(&path)
+                .map_err(|err| err.to_string())?
+                .ok_or(format!("No license file recognised at the root of \"{}\"", path))?;
+            let templater = LicenseTemplater::new(project_license, &license_config.choices)?;
+            let choices = templater.choices().iter().cloned().map(serde_json::Value::String).collect();
+            cookiecutter.insert(LICENSE_VARIABLE, serde_json::Value::Array(choices));
+            Some(templater)
+        },
+        None => None
+    };
+
+    // Generated files get the line endings the template files are normalised to
+    if let Some(new_line) = config.line_endings.new_line() {
+        cookiecutter.insert("_new_lines", serde_json::Value::String(new_line.to_string()));
     }
 
+    // Structured files are only replaced within the values at their document paths
+    let structured = match config.structured.is_empty() {
+        true => None,
+        false => Some(StructuredTemplater::new(&config.structured)?)
+    };
+
+    // Resolve the conditional paths before the replacer is consumed
+    let conditional_paths = resolve_conditional_paths(
+        Path::new(&path),
+        &config.conditional_paths,
+        &replacer,
+        &cookiecutter)?;
+
+    // Check if current dir is empty
+    ensure_empty_current_dir(&[COOKIECUTTER_JSON, CUTTERCOOKIE_JSON])?;
+
+    let secrets_policy = config.secrets.policy;
+    let options = ProcessOptionsBuilder::default()
+        .strip_directives(args.strip_directives)
+        .jobs(args.jobs)
+        .report_overlaps(args.report_overlaps)
+        .package_paths(package_paths)
+        .dates(dates)
+        .license(license)
+        .secrets(Some(SecretScanner::new(secrets_policy)))
+        .structured(structured)
+        .line_endings(config.line_endings)
+        .encodings(EncodingDetector::new(&config.encodings)?)
+        .build()
+        .map_err(|err| err.to_string())?;
+
     // process files
-    match process_files(
-        args.path.as_str(),
+    let report = process_files(
+        path.as_str(),
         &dest_path.display().to_string(),
         args.excluded_items,
         replacer,
-        !args.no_root)
-    {
-        Ok(_) => Ok(()),
-        Err(err) => Err(format!("Proccess had error : {}", err))
+        !args.no_root,
+        options)
+        .map_err(|err| format!("Proccess had error : {}", err))?;
+    print_report(&report);
+
+    // Replaced secrets are prompted for, empty by default
+    for finding in &report.secrets {
+        if let Some(variable) = &finding.variable {
+            cookiecutter.insert_default(variable, serde_json::Value::String(String::new()));
+        }
+    }
+
+    // Undeclared variables of the markers would fail the generation, they become flags
+    for variable in &report.variables {
+        cookiecutter.insert_default(variable, serde_json::Value::Bool(true));
+    }
+
+    // Generate the hook removing the conditional paths
+    if !conditional_paths.is_empty() {
+        write_post_gen_hook(&dest_path, &conditional_paths)?;
+    }
+
+    if cookiecutter != original_cookiecutter {
+        cookiecutter.save(&json_path)
+            .map_err(|err| err.to_string())?;
     }
+    Ok(())
 }
\ No newline at end of file
@@ -1,7 +1,4 @@
-mod cli;
-mod tool;
-
-use cli::entry_point;
+use cuttercookie::cli::entry_point;
 
 fn main() {
     match entry_point() {
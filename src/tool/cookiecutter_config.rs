@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// The cookiecutter.json emitted next to the generated template
+///
+/// Keeps the keys in insertion order, which is the order cookiecutter prompts them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CookiecutterConfig {
+    entries: Map<String, Value>
+}
+
+impl CookiecutterConfig {
+    /// Creates an empty configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads an existing cookiecutter.json, or returns an empty configuration if it is missing
+    ///
+    /// # Errors
+    /// * Returns error if the file exists but is not a JSON object
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Self::new())
+        }
+        match serde_json::from_str(&fs::read_to_string(path)?)? {
+            Value::Object(entries) => Ok(Self { entries }),
+            _ => Err(anyhow::anyhow!("Expected JSON object in \"{}\"", path))
+        }
+    }
+
+    /// Sets a variable unless it is already defined
+    pub fn insert_default(&mut self, key: &str, value: Value) {
+        if !self.entries.contains_key(key) {
+            self.insert(key, value);
+        }
+    }
+
+    /// Returns the value of a variable
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key)
+    }
+
+    /// Sets a variable, replacing any previous value
+    pub fn insert(&mut self, key: &str, value: Value) {
+        self.entries.insert(key.to_string(), value);
+    }
+
+    /// Adds a Jinja extension to the `_extensions` list unless it is already listed
+    pub fn add_extension(&mut self, extension: &str) {
+        let extensions = self.entries
+            .entry("_extensions")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(list) = extensions {
+            if !list.iter().any(|item| item == extension) {
+                list.push(Value::String(extension.to_string()));
+            }
+        }
+    }
+
+    /// Writes the configuration as pretty printed JSON
+    pub fn save(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.entries)?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_keeps_order() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("cookiecutter.json");
+        let path = path.to_str().unwrap();
+
+        let mut config = CookiecutterConfig::new();
+        config.insert("zeta", Value::String("last".to_string()));
+        config.insert("alpha", Value::Bool(true));
+        config.save(path)?;
+
+        let saved = fs::read_to_string(path)?;
+        let parsed: Value = serde_json::from_str(&saved)?;
+        assert_eq!(parsed["zeta"], "last");
+        assert_eq!(parsed["alpha"], true);
+        assert!(saved.find("zeta") < saved.find("alpha"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_existing_and_missing_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("cookiecutter.json");
+        let path = path.to_str().unwrap();
+        fs::write(path, r#"{"project_name": "demo"}"#)?;
+
+        let mut config = CookiecutterConfig::load(path)?;
+        config.insert_default("project_name", Value::String("other".to_string()));
+        config.insert_default("use_docker", Value::Bool(true));
+        config.save(path)?;
+
+        let parsed: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        assert_eq!(parsed["project_name"], "demo");
+        assert_eq!(parsed["use_docker"], true);
+        assert_eq!(CookiecutterConfig::load("nonexistent_cookiecutter.json")?, CookiecutterConfig::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_extension_once() {
+        let mut config = CookiecutterConfig::new();
+        config.add_extension("jinja2_time.TimeExtension");
+        config.add_extension("jinja2_time.TimeExtension");
+
+        assert_eq!(
+            config.get("_extensions"),
+            Some(&Value::Array(vec![Value::String("jinja2_time.TimeExtension".to_string())]))
+        );
+    }
+}
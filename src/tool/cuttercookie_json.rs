@@ -1,23 +1,155 @@
+use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 
+use serde::Deserialize;
 use serde_json::Value;
 use anyhow::Result;
 
+use super::line_endings::LineEndings;
+use super::secrets::SecretPolicy;
+use super::substitution_rule::{Boundary, RuleOptions, RuleScope, RuleTarget};
 use super::{SubstitutionRule, RegexReplacer};
 
+/// A source path only kept in generated projects when its condition holds
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionalPathConfig {
+    pub path: String,  // Path relative to the project root, file or directory
+    #[serde(rename = "if")]
+    pub condition: String,  // Condition on boolean or choice variables, e.g. `ci == 'gitlab'`
+}
+
+/// Files scanned by an optional detection, such as GUIDs or dates
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileFilterConfig {
+    pub include: Vec<String>,  // Glob patterns of the scanned files, every file when empty
+}
+
+/// Licenses offered by the `open_source_license` choice variable
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LicenseConfig {
+    pub choices: Vec<String>,  // Bundled licenses offered besides the project one, every one when empty
+}
+
+/// Handling of the secrets found in the project
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretConfig {
+    pub policy: SecretPolicy,  // `fail`, `exclude` or `variable`
+}
+
+/// Files whose replacements are restricted to values at document paths
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StructuredConfig {
+    pub files: String,  // Glob pattern of the JSON, TOML or YAML files
+    pub paths: Vec<String>,  // Document paths of the replaced values, e.g. `tool.poetry.authors[*]`
+}
+
+/// Files read and written in an encoding that cannot be guessed
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EncodingConfig {
+    pub files: String,  // Glob pattern of the files
+    pub encoding: String,  // Label of their encoding, e.g. `windows-1252` or `shift_jis`
+}
+
+/// Options of the rule of a variable
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    pub include: Vec<String>,  // Glob patterns of the files whose contents are replaced, every file when empty
+    pub exclude: Vec<String>,  // Glob patterns of the files whose contents are never replaced
+    pub apply_to: RuleTarget,  // `both`, `paths` or `contents`
+    pub boundary: Option<Boundary>,  // `none`, `word` or `identifier`, the global boundary when absent
+    pub ignore_case: bool,  // Matches any casing, reproduced with a Jinja filter
+    pub priority: i32,  // Wins over lower priorities among matches starting at the same position
+    pub normalize: Option<bool>,  // Matches the NFC and NFD forms of the value, the global setting when absent
+}
+
+impl RuleConfig {
+    /// Builds the options of the rule
+    ///
+    /// # Arguments
+    /// * `boundary` - Global boundary, used when the rule sets none
+    /// * `normalize` - Global normalisation setting, used when the rule sets none
+    ///
+    /// # Errors
+    /// * Returns error if a glob pattern is invalid
+    pub fn options(&self, boundary: Boundary, normalize: bool) -> Result<RuleOptions, String> {
+        Ok(RuleOptions {
+            scope: RuleScope::new(&self.include, &self.exclude, self.apply_to)?,
+            boundary: self.boundary.unwrap_or(boundary),
+            ignore_case: self.ignore_case,
+            priority: self.priority,
+            normalize: self.normalize.unwrap_or(normalize)
+        })
+    }
+}
+
+/// Options read from the optional `cuttercookie.json` placed next to `cookiecutter.json`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CuttercookieConfig {
+    /// Paths removed by a generated post generation hook when their condition is false
+    pub conditional_paths: Vec<ConditionalPathConfig>,
+    /// Variables holding a dotted package name, laid out as nested directories
+    pub package_paths: Vec<String>,
+    /// GUIDs replaced by values generated for every project, disabled when absent
+    pub guids: Option<FileFilterConfig>,
+    /// Copyright years and changelog dates replaced by the generation date, disabled when absent
+    pub dates: Option<FileFilterConfig>,
+    /// License of the project replaced by a choice of licenses, disabled when absent
+    pub licenses: Option<LicenseConfig>,
+    /// Policy applied to the secrets found while templatizing
+    pub secrets: SecretConfig,
+    /// Structured files only replaced within the string values at given document paths
+    pub structured: Vec<StructuredConfig>,
+    /// Options of the rules, by variable
+    pub rules: HashMap<String, RuleConfig>,
+    /// Boundary of the variable rules without their own
+    pub boundary: Boundary,
+    /// Line endings of the template files, also generated by cookiecutter when normalised
+    pub line_endings: LineEndings,
+    /// Encodings of the files whose encoding cannot be guessed, the first matching glob winning
+    pub encodings: Vec<EncodingConfig>,
+    /// Matching under Unicode normalisation equivalence of the variable rules without their own setting
+    pub normalize: bool,
+}
+
+/// Reads the cuttercookie options, or returns the defaults if the file is missing
+///
+/// # Arguments
+/// * `path` - Path to the `cuttercookie.json` file
+///
+/// # Errors
+/// * Returns error if the file exists but is not valid JSON or holds unknown options
+pub fn load_config(path: &str) -> Result<CuttercookieConfig> {
+    if !Path::new(path).exists() {
+        return Ok(CuttercookieConfig::default())
+    }
+    let json_str = fs::read_to_string(path)?;
+    serde_json::from_str(&json_str)
+        .map_err(|err| anyhow::anyhow!("Invalid \"{}\": {}", path, err))
+}
+
 /// Parses a JSON string into a RegexReplacer containing substitution rules
 ///
 /// # Arguments
 /// * `json_str` - JSON string containing pattern-replacement pairs where:
 ///   - Keys are replacement strings
-///   - Values are regex patterns
+///   - Values are regex patterns, or booleans and choice lists that produce no rule
+///   - Private variables, whose keys start with `_`, produce no rule
 ///
 /// # Returns
 /// * `Result<RegexReplacer>` - A RegexReplacer configured with the parsed rules
 ///
 /// # Errors
 /// * Returns error if input is not a valid JSON object
-/// * Returns error if any value in the JSON is not a string
+/// * Returns error if any value in the JSON is not a string, a boolean or a list
 fn parse_json_pairs(json_str: &str) -> Result<RegexReplacer> {
     let parsed: Value = serde_json::from_str(json_str)?;
 
@@ -25,9 +157,10 @@ fn parse_json_pairs(json_str: &str) -> Result<RegexReplacer> {
         Value::Object(map) => {
             let subs = map
                 .into_iter()
+                .filter(|(key, value)| !key.starts_with('_') && !value.is_boolean() && !value.is_array())
                 .map(|(replacement, value)| {
                     let pattern = value.as_str()
-                        .ok_or_else(|| anyhow::anyhow!("Value must be a string"))?
+                        .ok_or_else(|| anyhow::anyhow!("Value must be a string, a boolean or a list of choices"))?
                         .to_string();
                     Ok(SubstitutionRule::new(pattern, replacement))
                 })
@@ -108,6 +241,22 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_boolean_and_choice_variables() -> Result<()> {
+        let json_str = r#"{
+            "greeting": "hello world",
+            "use_docker": true,
+            "ci": ["github", "gitlab"],
+            "__greeting_path": "{{ cookiecutter.greeting.replace(' ', '/') }}",
+            "_extensions": ["jinja2_time.TimeExtension"]
+        }"#;
+
+        let replacer = parse_json_pairs(json_str)?;
+
+        assert_eq!(replacer.replace("hello world true"), "{{cookiecutter.greeting}} true");
+        Ok(())
+    }
+
     #[test]
     fn test_parse_invalid_json() {
         let invalid_jsons = [
@@ -157,4 +306,57 @@ mod test {
         assert!(result.is_err());
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_load_config() -> Result<()> {
+        let temp_file = create_temp_json_file(r#"{
+            "conditional_paths": [{"path": "docker", "if": "use_docker"}],
+            "package_paths": ["package_name"],
+            "guids": {"include": ["*.sln"]},
+            "dates": {},
+            "licenses": {"choices": ["MIT"]},
+            "secrets": {"policy": "variable"},
+            "structured": [{"files": "pyproject.toml", "paths": ["project.name"]}],
+            "rules": {"api": {"include": ["*.toml"], "apply_to": "contents", "boundary": "identifier", "ignore_case": true, "priority": 2, "normalize": false}},
+            "boundary": "word",
+            "line_endings": "crlf",
+            "encodings": [{"files": "legacy/**/*.pas", "encoding": "windows-1252"}],
+            "normalize": true
+        }"#)?;
+
+        let config = load_config(temp_file.path().to_str().unwrap())?;
+
+        assert_eq!(config.conditional_paths, vec![ConditionalPathConfig {
+            path: "docker".to_string(),
+            condition: "use_docker".to_string()
+        }]);
+        assert_eq!(config.package_paths, vec!["package_name".to_string()]);
+        assert_eq!(config.guids, Some(FileFilterConfig { include: vec!["*.sln".to_string()] }));
+        assert_eq!(config.dates, Some(FileFilterConfig::default()));
+        assert_eq!(config.licenses, Some(LicenseConfig { choices: vec!["MIT".to_string()] }));
+        assert_eq!(config.secrets.policy, SecretPolicy::Variable);
+        assert_eq!(config.structured, vec![StructuredConfig {
+            files: "pyproject.toml".to_string(),
+            paths: vec!["project.name".to_string()]
+        }]);
+        assert_eq!(config.rules["api"], RuleConfig {
+            include: vec!["*.toml".to_string()],
+            exclude: Vec::new(),
+            apply_to: RuleTarget::Contents,
+            boundary: Some(Boundary::Identifier),
+            ignore_case: true,
+            priority: 2,
+            normalize: Some(false)
+        });
+        assert_eq!(config.boundary, Boundary::Word);
+        assert_eq!(config.line_endings, LineEndings::Crlf);
+        assert!(config.normalize);
+        assert_eq!(config.encodings, vec![EncodingConfig {
+            files: "legacy/**/*.pas".to_string(),
+            encoding: "windows-1252".to_string()
+        }]);
+        assert_eq!(load_config("nonexistent_cuttercookie.json")?, CuttercookieConfig::default());
+        assert!(load_config(create_temp_json_file(r#"{"unknown": 1}"#)?.path().to_str().unwrap()).is_err());
+        Ok(())
+    }
+}
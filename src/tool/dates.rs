@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{Captures, Regex};
+
+use super::segment::{replace_in_lines, Segment};
+
+/// Jinja tag rendering the current year
+const YEAR_TAG: &str = "{% now 'utc', '%Y' %}";
+
+/// Jinja tag rendering the current ISO date
+const DATE_TAG: &str = "{% now 'utc', '%Y-%m-%d' %}";
+
+/// Extension providing the `{% now %}` tag, listed in the `_extensions` of cookiecutter.json
+pub const TIME_EXTENSION: &str = "jinja2_time.TimeExtension";
+
+/// File name prefixes of changelogs, whose dates are all replaced
+const CHANGELOG_PREFIXES: [&str; 4] = ["CHANGELOG", "CHANGES", "HISTORY", "NEWS"];
+
+/// Replaces the years and dates of copyright lines and changelogs with the generation date
+#[derive(Debug, Clone)]
+pub struct DateTemplater {
+    include: GlobSet,  // Relative paths processed, every file when empty
+    copyright_regex: Regex,  // Copyright notices at the start of a line or of a comment
+    date_regex: Regex,  // ISO dates, years and year ranges
+}
+
+impl DateTemplater {
+    /// Creates a templater restricted to the files matching the glob patterns
+    ///
+    /// # Errors
+    /// * Returns error if a glob pattern is invalid
+    pub fn new(include: &[String]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in include {
+            builder.add(Glob::new(pattern)?);
+        }
+        Ok(Self {
+            include: builder.build()?,
+            // Notices start the line or a comment, so that code such as `isdigit(c)` is left alone
+            copyright_regex: Regex::new(
+                r"(?i)(?:^\s*(?:[#;%'*!-]+|//+|/\*+|<!--|\{#|\.\.)?|//+|/\*+|<!--|#)\s*\**\s*(?:spdx-file)?(?:copyright|©|\(c\))"
+            ).expect("Invalid copyright regex"),
+            date_regex: Regex::new(r"\b(\d{4}-\d{2}-\d{2})\b|\b(?:19|20)\d{2}(?:\s*[-–,]\s*(?:19|20)\d{2})*\b")
+                .expect("Invalid date regex")
+        })
+    }
+
+    /// Tells whether a file is processed, which `apply` may change
+    pub fn targets(&self, rpath: &Path) -> bool {
+        self.include.is_empty() || self.include.is_match(rpath)
+    }
+
+    /// Splits the text segments of a file around the dates to replace
+    ///
+    /// # Arguments
+    /// * `rpath` - Relative path of the file
+    /// * `segments` - Segments of the file content
+    ///
+    /// # Returns
+    /// * The segments with `{% now %}` literals in place of the dates:
+    ///   - years, year ranges and ISO dates on copyright lines
+    ///   - every ISO date of changelogs
+    pub fn apply(&self, rpath: &Path, segments: Vec<Segment>) -> Vec<Segment> {
+        if !self.targets(rpath) {
+            return segments
+        }
+        let changelog = rpath.file_name()
+            .map(|name| name.to_string_lossy().to_uppercase())
+            .is_some_and(|name| CHANGELOG_PREFIXES.iter().any(|prefix| name.starts_with(prefix)));
+
+        replace_in_lines(segments, |line| {
+            let copyright = self.copyright_regex.is_match(line);
+            if !copyright && !changelog {
+                return Vec::new()
+            }
+            self.date_regex.captures_iter(line)
+                .filter_map(|caps| {
+                    let tag = date_tag(&caps, copyright)?;
+                    let m = caps.get(0).expect("Match without group 0");
+                    Some((m.range(), tag.to_string()))
+                })
+                .collect()
+        })
+    }
+}
+
+/// Returns the tag replacing a date match, years being only replaced on copyright lines
+fn date_tag(caps: &Captures, copyright: bool) -> Option<&'static str> {
+    match caps.get(1) {
+        Some(_) => Some(DATE_TAG),
+        None if copyright => Some(YEAR_TAG),
+        None => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(segments: &[Segment]) -> String {
+        segments.iter()
+            .map(|segment| match segment {
+                Segment::Text(text) | Segment::Verbatim(text) => text.clone(),
+                Segment::Literal(literal) => format!("<{}>", literal)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_copyright_years() {
+        let templater = DateTemplater::new(&[]).unwrap();
+        let segments = templater.apply(
+            Path::new("src/main.rs"),
+            vec![Segment::Text("// Copyright (c) 2019-2023 Acme\nlet port = 2019;\n".to_string())]
+        );
+
+        assert_eq!(
+            render(&segments),
+            "// Copyright (c) <{% now 'utc', '%Y' %}> Acme\nlet port = 2019;\n"
+        );
+    }
+
+    #[test]
+    fn test_copyright_notices() {
+        let templater = DateTemplater::new(&[]).unwrap();
+        let notices = [
+            "(C) 2020 Acme", "# SPDX-FileCopyrightText: 2020 Acme", " * Copyright 2020 Acme",
+            "<!-- © 2020 Acme -->", "let port = 80; // (c) 2020 Acme"
+        ];
+        for notice in notices {
+            let segments = templater.apply(Path::new("src/main.rs"), vec![Segment::Text(notice.to_string())]);
+            assert_eq!(render(&segments), notice.replace("2020", "<{% now 'utc', '%Y' %}>"));
+        }
+
+        let code = "if isdigit(c) and year == 2019: f(c, 2020)\nprintf(\"(c) %d\", 2021);\n";
+        let segments = vec![Segment::Text(code.to_string())];
+        assert_eq!(templater.apply(Path::new("src/main.py"), segments.clone()), segments);
+    }
+
+    #[test]
+    fn test_changelog_dates() {
+        let templater = DateTemplater::new(&[]).unwrap();
+        let segments = templater.apply(
+            Path::new("CHANGELOG.md"),
+            vec![
+                Segment::Text("## 1.0.0 - 2023-05-01\n".to_string()),
+                Segment::Literal("2023-05-01".to_string())
+            ]
+        );
+
+        assert_eq!(
+            render(&segments),
+            "## 1.0.0 - <{% now 'utc', '%Y-%m-%d' %}>\n<2023-05-01>"
+        );
+    }
+
+    #[test]
+    fn test_include_globs() {
+        let templater = DateTemplater::new(&["LICENSE*".to_string()]).unwrap();
+        let segments = vec![Segment::Text("Copyright 2023\n".to_string())];
+
+        assert_eq!(templater.apply(Path::new("README.md"), segments.clone()), segments);
+        assert_ne!(templater.apply(Path::new("LICENSE"), segments.clone()), segments);
+    }
+}
@@ -0,0 +1,211 @@
+use std::ops::Range;
+
+/// A block of a diff between two sequences `a` and `b`
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffOp {
+    Equal { a: Range<usize>, b: Range<usize> },  // Items identical in both sequences
+    Change { a: Range<usize>, b: Range<usize> },  // Items of `a` replaced by items of `b`, either may be empty
+}
+
+/// Computes the shortest edit script between two sequences using Myers' algorithm
+///
+/// # Arguments
+/// * `a` - Original sequence
+/// * `b` - Modified sequence
+///
+/// # Returns
+/// * Blocks covering both sequences in order, where consecutive deletions and
+///   insertions are merged into a single `Change` block
+pub fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+    // Trim the common prefix and suffix, which keeps the search space small
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (n, m) = (a.len() - prefix - suffix, b.len() - prefix - suffix);
+
+    let mut ops = Vec::new();
+    if prefix > 0 {
+        ops.push(DiffOp::Equal { a: 0..prefix, b: 0..prefix });
+    }
+    for (x, y, step) in edit_script(&a[prefix..prefix + n], &b[prefix..prefix + m]) {
+        push_step(&mut ops, prefix + x, prefix + y, step);
+    }
+    if suffix > 0 {
+        match ops.last_mut() {
+            Some(DiffOp::Equal { a: ra, b: rb }) if ra.end == a.len() - suffix => {
+                ra.end = a.len();
+                rb.end = b.len();
+            },
+            _ => ops.push(DiffOp::Equal { a: a.len() - suffix..a.len(), b: b.len() - suffix..b.len() })
+        }
+    }
+    ops
+}
+
+/// A single move of the edit script
+#[derive(Clone, Copy)]
+enum Step {
+    Keep,  // `a[x]` and `b[y]` are identical
+    Delete,  // `a[x]` is removed
+    Insert,  // `b[y]` is added
+}
+
+/// Appends one edit step at `(x, y)`, extending the last block when it has the same kind
+fn push_step(ops: &mut Vec<DiffOp>, x: usize, y: usize, step: Step) {
+    let (da, db) = match step {
+        Step::Keep => (1, 1),
+        Step::Delete => (1, 0),
+        Step::Insert => (0, 1)
+    };
+    match (ops.last_mut(), step) {
+        (Some(DiffOp::Equal { a, b }), Step::Keep) if a.end == x && b.end == y => {
+            a.end += da;
+            b.end += db;
+        },
+        (Some(DiffOp::Change { a, b }), Step::Delete | Step::Insert) if a.end == x && b.end == y => {
+            a.end += da;
+            b.end += db;
+        },
+        (_, Step::Keep) => ops.push(DiffOp::Equal { a: x..x + da, b: y..y + db }),
+        (_, _) => ops.push(DiffOp::Change { a: x..x + da, b: y..y + db })
+    }
+}
+
+/// Largest number of inserted and deleted items searched for, bounding the trace
+/// to a few megabytes; sequences differing more are replaced as a whole
+const MAX_EDIT_COST: usize = 2048;
+
+/// Runs Myers' algorithm and returns the steps from the start of both sequences
+///
+/// Each round only keeps the diagonals it reached, and sequences whose shortest
+/// edit script exceeds `MAX_EDIT_COST` are deleted and inserted entirely.
+fn edit_script<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize, Step)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    'search: for d in 0..=max.min(MAX_EDIT_COST) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        let deletions = (0..a.len()).map(|x| (x, 0, Step::Delete));
+        return deletions.chain((0..b.len()).map(|y| (a.len(), y, Step::Insert))).collect()
+    }
+
+    // Walk the trace backwards to recover the path
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let diagonal = |k: isize| v[(k + d) as usize];  // Rounds keep the diagonals `-d..=d`
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && diagonal(k - 1) < diagonal(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = if d > 0 { diagonal(prev_k) } else { 0 };
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            steps.push((x as usize, y as usize, Step::Keep));
+        }
+        if d > 0 {
+            let step = if x == prev_x { Step::Insert } else { Step::Delete };
+            steps.push((prev_x as usize, prev_y as usize, step));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    steps.reverse();
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rebuilds `b` from `a` and the diff blocks to check the script is complete
+    fn apply(a: &[char], b: &[char], ops: &[DiffOp]) -> Vec<char> {
+        let mut out = Vec::new();
+        for op in ops {
+            match op {
+                DiffOp::Equal { a: ra, b: rb } => {
+                    assert_eq!(&a[ra.clone()], &b[rb.clone()]);
+                    out.extend_from_slice(&a[ra.clone()]);
+                },
+                DiffOp::Change { b: rb, .. } => out.extend_from_slice(&b[rb.clone()])
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_identical_sequences() {
+        let a: Vec<char> = "abc".chars().collect();
+        assert_eq!(diff(&a, &a), vec![DiffOp::Equal { a: 0..3, b: 0..3 }]);
+    }
+
+    #[test]
+    fn test_single_change() {
+        let a: Vec<&str> = vec!["name", "=", "acme", ";"];
+        let b: Vec<&str> = vec!["name", "=", "blue", "gadget", ";"];
+        assert_eq!(diff(&a, &b), vec![
+            DiffOp::Equal { a: 0..2, b: 0..2 },
+            DiffOp::Change { a: 2..3, b: 2..4 },
+            DiffOp::Equal { a: 3..4, b: 4..5 },
+        ]);
+    }
+
+    #[test]
+    fn test_script_rebuilds_target() {
+        let cases = [("ABCABBA", "CBABAC"), ("", "abc"), ("abc", ""), ("kitten", "sitting")];
+        for (a, b) in cases {
+            let a: Vec<char> = a.chars().collect();
+            let b: Vec<char> = b.chars().collect();
+            let ops = diff(&a, &b);
+            assert_eq!(apply(&a, &b, &ops), b);
+
+            // Blocks must tile both sequences without gaps
+            let (mut ea, mut eb) = (0, 0);
+            for op in &ops {
+                let (DiffOp::Equal { a: ra, b: rb } | DiffOp::Change { a: ra, b: rb }) = op;
+                assert_eq!((ra.start, rb.start), (ea, eb));
+                (ea, eb) = (ra.end, rb.end);
+            }
+            assert_eq!((ea, eb), (a.len(), b.len()));
+        }
+    }
+
+    #[test]
+    fn test_large_different_sequences() {
+        let a: Vec<String> = (0..20_000).map(|i| format!("a{}", i)).collect();
+        let b: Vec<String> = (0..20_000).map(|i| format!("b{}", i)).collect();
+        assert_eq!(diff(&a, &b), vec![DiffOp::Change { a: 0..20_000, b: 0..20_000 }]);
+
+        let mut c = a.clone();
+        c[10_000] = "changed".to_string();
+        assert_eq!(diff(&a, &c)[1], DiffOp::Change { a: 10_000..10_001, b: 10_000..10_001 });
+    }
+}
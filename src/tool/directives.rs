@@ -0,0 +1,357 @@
+use regex::Regex;
+
+use super::segment::Segment;
+
+/// Prefix shared by every in-source directive
+pub const DIRECTIVE_PREFIX: &str = "cuttercookie:";
+
+/// Words of a marker condition that are Jinja operators or constants, not variables
+const CONDITION_KEYWORDS: [&str; 11] = ["and", "or", "not", "in", "is", "true", "false", "none", "True", "False", "None"];
+
+/// Builds the regex recognising ignore directives anywhere in a line
+fn ignore_regex() -> Regex {
+    Regex::new(r"cuttercookie:(ignore-line|ignore-next-line|ignore-file)\b").expect("Invalid ignore regex")
+}
+
+/// Removes the comment holding an ignore directive from a line
+///
+/// # Returns
+/// * `None` when the line only held the directive comment and disappears entirely
+fn strip_ignore_comment(line: &str) -> Option<String> {
+    let comment = Regex::new(r"\s*(?:#|//|/\*|<!--|--|;|%|'|\{#|REM\b)?\s*cuttercookie:ignore-(?:line|next-line|file)\b\s*(?:\*/|-->|#\})?")
+        .expect("Invalid ignore comment regex");
+    let body = line.trim_end_matches(['\r', '\n']);
+    let ending = &line[body.len()..];
+    let stripped = comment.replace(body, "");
+    match stripped.trim().is_empty() {
+        true => None,
+        false => Some(format!("{}{}", stripped, ending))
+    }
+}
+
+/// Block opened by a marker and still waiting for its closing marker
+enum OpenBlock {
+    If { line: usize, has_else: bool },
+    Remove { line: usize },
+}
+
+/// Builds the regex recognising marker lines
+///
+/// A marker is a comment alone on its line: optional indentation and comment
+/// leader, the directive, its argument, then an optional comment closer.
+fn marker_regex() -> Regex {
+    Regex::new(r"^\s*(?:#|//|/\*|<!--|--|;|%|'|\{#|REM\b)?\s*cuttercookie:(if|elif|else|endif|remove-start|remove-end)\b(.*?)\s*(?:\*/|-->|#\})?\s*$")
+        .expect("Invalid marker regex")
+}
+
+/// Matches the string literals and identifiers of a condition
+fn condition_token_regex() -> Regex {
+    Regex::new(r#"'[^']*'|"[^"]*"|[A-Za-z_][A-Za-z0-9_.]*"#).expect("Invalid condition regex")
+}
+
+/// Tells if a condition token names a variable, rather than a literal or a keyword
+fn is_condition_variable(word: &str) -> bool {
+    let is_literal = word.starts_with('\'') || word.starts_with('"');
+    !is_literal && !CONDITION_KEYWORDS.contains(&word)
+}
+
+/// Turns a condition into a Jinja expression on cookiecutter variables
+///
+/// Bare identifiers are prefixed with `cookiecutter.`, keywords and string literals are kept.
+pub fn translate_condition(condition: &str) -> String {
+    condition_token_regex().replace_all(condition, |caps: &regex::Captures| {
+        let word = &caps[0];
+        if is_condition_variable(word) && !word.starts_with("cookiecutter.") {
+            format!("cookiecutter.{}", word)
+        } else {
+            word.to_string()
+        }
+    }).into_owned()
+}
+
+/// Returns the names of the cookiecutter variables a condition reads
+pub fn condition_variables(condition: &str) -> Vec<String> {
+    condition_token_regex().find_iter(condition)
+        .map(|m| m.as_str())
+        .filter(|word| is_condition_variable(word))
+        .map(|word| word.trim_start_matches("cookiecutter.").to_string())
+        .collect()
+}
+
+/// Returns the cookiecutter variables read by the `if` and `elif` markers of a content
+///
+/// Markers inside removed regions are skipped, as they never reach the template.
+pub fn marker_variables(content: &str) -> Vec<String> {
+    if !content.contains(DIRECTIVE_PREFIX) {
+        return Vec::new()
+    }
+    let marker = marker_regex();
+    let mut variables = Vec::new();
+    let mut removing = 0usize;
+    for caps in content.lines().filter_map(|line| marker.captures(line)) {
+        match &caps[1] {
+            "remove-start" => removing += 1,
+            "remove-end" => removing = removing.saturating_sub(1),
+            "if" | "elif" if removing == 0 => variables.extend(condition_variables(caps[2].trim())),
+            _ => ()
+        }
+    }
+    variables
+}
+
+/// Splits a file content into segments according to its marker and ignore directives
+///
+/// # Arguments
+/// * `content` - Content of the source file
+/// * `strip_directives` - Remove the ignore directive comments from the template
+///
+/// # Returns
+/// * `Result<Vec<Segment>, String>` - Text segments for the source lines, literal Jinja tags
+///   for the markers, or an error naming the line of an unbalanced marker
+///
+/// # Behavior
+/// * `cuttercookie:if <condition>`, `elif <condition>`, `else` and `endif` marker lines
+///   are replaced by the matching `{% if %}` tags, conditions naming cookiecutter variables
+/// * Lines from `cuttercookie:remove-start` to `cuttercookie:remove-end`, markers
+///   included, are stripped from the template
+/// * Marker lines disappear entirely: tags are glued to the following line, so a
+///   generated project renders exactly the lines of the selected branches
+/// * A line holding `cuttercookie:ignore-line`, the line following
+///   `cuttercookie:ignore-next-line`, and the whole file once `cuttercookie:ignore-file`
+///   appears anywhere, become verbatim segments the rules never apply to, still
+///   read by the secret scanner
+pub fn parse_directives(content: &str, strip_directives: bool) -> Result<Vec<Segment>, String> {
+    if !content.contains(DIRECTIVE_PREFIX) {
+        return Ok(vec![Segment::Text(content.to_string())])
+    }
+
+    let (marker, ignore) = (marker_regex(), ignore_regex());
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut text = String::new();
+    let mut stack: Vec<OpenBlock> = Vec::new();
+    let mut removing = 0;
+    let (mut ignore_next, mut ignore_file) = (false, false);
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        let caps = match marker.captures(line.trim_end_matches(['\r', '\n'])) {
+            Some(caps) => caps,
+            None => {
+                if removing > 0 {
+                    continue
+                }
+                let directive = ignore.captures(line).map(|caps| caps[1].to_string());
+                let kept = match (&directive, strip_directives) {
+                    (Some(_), true) => strip_ignore_comment(line),
+                    _ => Some(line.to_string())
+                };
+                match directive.as_deref() {
+                    Some("ignore-line") => {
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        segments.extend(kept.map(Segment::Verbatim));
+                    },
+                    Some(directive) => {
+                        ignore_next |= directive == "ignore-next-line";
+                        ignore_file |= directive == "ignore-file";
+                        text.extend(kept);
+                    },
+                    None if ignore_next => {
+                        ignore_next = false;
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        segments.push(Segment::Verbatim(line.to_string()));
+                    },
+                    None => text.push_str(line)
+                }
+                continue
+            }
+        };
+        let (directive, argument) = (&caps[1], caps[2].trim());
+
+        // Inside a removed region only nested removals are tracked
+        if removing > 0 && !directive.starts_with("remove-") {
+            continue
+        }
+
+        let tag = match directive {
+            "if" | "elif" if argument.is_empty() => {
+                return Err(format!("line {}: `{}{}` needs a condition", line_number, DIRECTIVE_PREFIX, directive))
+            },
+            "if" => {
+                stack.push(OpenBlock::If { line: line_number, has_else: false });
+                Some(format!("{{% if {} %}}", translate_condition(argument)))
+            },
+            "elif" | "else" => match stack.last_mut() {
+                Some(OpenBlock::If { has_else: false, .. }) if directive == "elif" => {
+                    Some(format!("{{% elif {} %}}", translate_condition(argument)))
+                },
+                Some(OpenBlock::If { has_else, .. }) if !*has_else => {
+                    *has_else = true;
+                    Some("{% else %}".to_string())
+                },
+                _ => return Err(format!("line {}: unexpected `{}{}`", line_number, DIRECTIVE_PREFIX, directive))
+            },
+            "endif" => match stack.pop() {
+                Some(OpenBlock::If { .. }) => Some("{% endif %}".to_string()),
+                _ => return Err(format!("line {}: unexpected `{}endif`", line_number, DIRECTIVE_PREFIX))
+            },
+            "remove-start" => {
+                stack.push(OpenBlock::Remove { line: line_number });
+                removing += 1;
+                None
+            },
+            _ => match stack.pop() {
+                Some(OpenBlock::Remove { .. }) => {
+                    removing -= 1;
+                    None
+                },
+                _ => return Err(format!("line {}: unexpected `{}remove-end`", line_number, DIRECTIVE_PREFIX))
+            }
+        };
+
+        if let Some(tag) = tag {
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Literal(tag));
+        }
+    }
+
+    match stack.last() {
+        Some(OpenBlock::If { line, .. }) => Err(format!("line {}: `{}if` is never closed", line, DIRECTIVE_PREFIX)),
+        Some(OpenBlock::Remove { line }) => Err(format!("line {}: `{}remove-start` is never closed", line, DIRECTIVE_PREFIX)),
+        None => {
+            if !text.is_empty() {
+                segments.push(Segment::Text(text));
+            }
+            if ignore_file {
+                segments = segments.into_iter()
+                    .map(|segment| match segment {
+                        Segment::Text(text) => Segment::Verbatim(text),
+                        other => other
+                    })
+                    .collect();
+            }
+            Ok(segments)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Segment {
+        Segment::Text(s.to_string())
+    }
+
+    fn literal(s: &str) -> Segment {
+        Segment::Literal(s.to_string())
+    }
+
+    fn verbatim(s: &str) -> Segment {
+        Segment::Verbatim(s.to_string())
+    }
+
+    #[test]
+    fn test_content_without_directives() {
+        assert_eq!(parse_directives("plain\ncontent\n", false).unwrap(), vec![text("plain\ncontent\n")]);
+    }
+
+    #[test]
+    fn test_conditional_block() {
+        let content = "services:\n  # cuttercookie:if use_docker\n  docker: {}\n  # cuttercookie:else\n  local: {}\n  # cuttercookie:endif\nend\n";
+
+        assert_eq!(parse_directives(content, false).unwrap(), vec![
+            text("services:\n"),
+            literal("{% if cookiecutter.use_docker %}"),
+            text("  docker: {}\n"),
+            literal("{% else %}"),
+            text("  local: {}\n"),
+            literal("{% endif %}"),
+            text("end\n"),
+        ]);
+    }
+
+    #[test]
+    fn test_comment_styles_and_conditions() {
+        let content = "<!-- cuttercookie:if ci == 'github' and not private -->\nbadge\r\n/* cuttercookie:elif use_gitlab */\r\n// cuttercookie:endif\r\n";
+
+        assert_eq!(parse_directives(content, false).unwrap(), vec![
+            literal("{% if cookiecutter.ci == 'github' and not cookiecutter.private %}"),
+            text("badge\r\n"),
+            literal("{% elif cookiecutter.use_gitlab %}"),
+            literal("{% endif %}"),
+        ]);
+    }
+
+    #[test]
+    fn test_condition_variables() {
+        assert_eq!(condition_variables("ci == 'github' and not cookiecutter.private"), vec!["ci", "private"]);
+        assert!(condition_variables("true").is_empty());
+    }
+
+    #[test]
+    fn test_marker_variables() {
+        let content = "# cuttercookie:if use_docker and ci == 'github'\n# cuttercookie:elif cookiecutter.use_podman\n# cuttercookie:endif\n# cuttercookie:remove-start\n# cuttercookie:if internal\n# cuttercookie:remove-end\n";
+        assert_eq!(marker_variables(content), vec!["use_docker", "ci", "use_podman"]);
+        assert!(marker_variables("plain\n").is_empty());
+    }
+
+    #[test]
+    fn test_removed_region() {
+        let content = "keep\n# cuttercookie:remove-start\ninternal only\n# cuttercookie:if nested\n# cuttercookie:remove-end\nkeep too\n";
+
+        assert_eq!(parse_directives(content, false).unwrap(), vec![text("keep\nkeep too\n")]);
+    }
+
+    #[test]
+    fn test_markers_need_their_own_line() {
+        let content = "let marker = \"cuttercookie:if x\";\n";
+        assert_eq!(parse_directives(content, false).unwrap(), vec![text(content)]);
+    }
+
+    #[test]
+    fn test_unbalanced_markers() {
+        let errors = [
+            ("# cuttercookie:if x\n", "line 1"),
+            ("a\n# cuttercookie:endif\n", "line 2"),
+            ("# cuttercookie:remove-start\n", "line 1"),
+            ("# cuttercookie:if x\n# cuttercookie:else\n# cuttercookie:elif y\n", "line 3"),
+            ("# cuttercookie:if\n", "line 1"),
+        ];
+
+        for (content, location) in errors {
+            let err = parse_directives(content, false).unwrap_err();
+            assert!(err.starts_with(location), "{} for {:?}", err, content);
+        }
+    }
+
+    #[test]
+    fn test_ignore_line_directives() {
+        let content = "name = \"demo\" # cuttercookie:ignore-line\n// cuttercookie:ignore-next-line\ndemo = 1\ndemo = 2\n";
+
+        assert_eq!(parse_directives(content, false).unwrap(), vec![
+            verbatim("name = \"demo\" # cuttercookie:ignore-line\n"),
+            text("// cuttercookie:ignore-next-line\n"),
+            verbatim("demo = 1\n"),
+            text("demo = 2\n"),
+        ]);
+        assert_eq!(parse_directives(content, true).unwrap(), vec![
+            verbatim("name = \"demo\"\n"),
+            verbatim("demo = 1\n"),
+            text("demo = 2\n"),
+        ]);
+    }
+
+    #[test]
+    fn test_ignore_file_directive() {
+        let content = "<!-- cuttercookie:ignore-file -->\r\n# demo changelog\r\n";
+
+        assert_eq!(parse_directives(content, false).unwrap(), vec![verbatim(content)]);
+        assert_eq!(parse_directives(content, true).unwrap(), vec![verbatim("# demo changelog\r\n")]);
+    }
+}
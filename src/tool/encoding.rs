@@ -0,0 +1,186 @@
+use std::path::Path;
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use globset::{Glob, GlobMatcher};
+
+use super::cuttercookie_json::EncodingConfig;
+
+/// Bytes inspected to recognise UTF-16 text without byte-order mark
+const SNIFF_BYTES: usize = 4096;
+
+/// Encoding of a text file, used to write it back as it was read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,  // Encoding of the text
+    pub bom: bool,  // The file starts with a byte-order mark
+}
+
+impl TextEncoding {
+    /// Encodes a text, preceded by the byte-order mark if the source had one
+    ///
+    /// # Errors
+    /// * Returns error if the text holds characters the encoding cannot represent
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.bom {
+            bytes.extend_from_slice(bom_bytes(self.encoding));
+        }
+        if self.encoding == UTF_16LE {
+            bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        } else if self.encoding == UTF_16BE {
+            bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        } else {
+            let (encoded, _, had_errors) = self.encoding.encode(text);
+            if had_errors {
+                return Err(format!("the templatized text cannot be encoded in {}", self.encoding.name()))
+            }
+            bytes.extend_from_slice(&encoded);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Returns the byte-order mark of an encoding, empty for encodings without one
+fn bom_bytes(encoding: &'static Encoding) -> &'static [u8] {
+    match encoding {
+        encoding if encoding == UTF_8 => b"\xEF\xBB\xBF",
+        encoding if encoding == UTF_16LE => b"\xFF\xFE",
+        encoding if encoding == UTF_16BE => b"\xFE\xFF",
+        _ => b""
+    }
+}
+
+/// Decodes bytes without replacement characters
+///
+/// # Returns
+/// * The text and its encoding, `None` if the bytes are malformed in the encoding
+fn decode_as(encoding: &'static Encoding, bytes: &[u8], bom: bool) -> Option<(String, TextEncoding)> {
+    let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+    (!had_errors).then(|| (text.into_owned(), TextEncoding { encoding, bom }))
+}
+
+/// Recognises UTF-16 text without byte-order mark by the zero bytes of its ASCII characters
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    if !bytes.len().is_multiple_of(2) {
+        return None
+    }
+    let sniffed = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    let zeros = |parity: usize| sniffed.iter().skip(parity).step_by(2).filter(|byte| **byte == 0).count();
+    let (even, odd) = (zeros(0), zeros(1));
+    let units = sniffed.len() / 2;
+    match (even, odd) {
+        (even, odd) if odd * 10 >= units * 4 && even * 10 <= odd => Some(UTF_16LE),
+        (even, odd) if even * 10 >= units * 4 && odd * 10 <= even => Some(UTF_16BE),
+        _ => None
+    }
+}
+
+/// Detects the encoding of the files and decodes them
+///
+/// Encodings are recognised by their byte-order mark, then set by the configuration
+/// for the files matching a glob, then guessed: UTF-16 from its zero bytes, UTF-8 when
+/// valid, Windows-1252 otherwise, which also reads Latin-1. Other files holding zero
+/// bytes are binary.
+#[derive(Debug, Clone, Default)]
+pub struct EncodingDetector {
+    overrides: Vec<(GlobMatcher, &'static Encoding)>,  // Encoding of the files matching each glob
+}
+
+impl EncodingDetector {
+    /// Creates a detector from the `encodings` entries of `cuttercookie.json`
+    ///
+    /// # Errors
+    /// * Returns error if a glob pattern is invalid or an encoding is unknown
+    pub fn new(configs: &[EncodingConfig]) -> Result<Self, String> {
+        let overrides = configs.iter()
+            .map(|config| {
+                let files = Glob::new(&config.files).map_err(|err| err.to_string())?.compile_matcher();
+                let encoding = Encoding::for_label(config.encoding.as_bytes())
+                    .ok_or(format!("Unknown encoding \"{}\" for \"{}\"", config.encoding, config.files))?;
+                Ok((files, encoding))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { overrides })
+    }
+
+    /// Returns the encoding the configuration sets for a file, the first matching glob winning
+    pub fn override_for(&self, rpath: &Path) -> Option<&'static Encoding> {
+        self.overrides.iter()
+            .find(|(files, _)| files.is_match(rpath))
+            .map(|(_, encoding)| *encoding)
+    }
+
+    /// Decodes the content of a file
+    ///
+    /// # Arguments
+    /// * `rpath` - Relative path of the file in the source project
+    /// * `bytes` - Content of the file
+    ///
+    /// # Returns
+    /// * `Result<Option<(String, TextEncoding)>, String>` - The text without its byte-order
+    ///   mark and the encoding to write it back with, `None` for binary files
+    ///
+    /// # Errors
+    /// * Returns error if the file is malformed in the encoding set by the configuration
+    pub fn decode(&self, rpath: &Path, bytes: &[u8]) -> Result<Option<(String, TextEncoding)>, String> {
+        if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+            return Ok(decode_as(encoding, &bytes[bom_length..], true))
+        }
+        if let Some(encoding) = self.override_for(rpath) {
+            return decode_as(encoding, bytes, false)
+                .map(Some)
+                .ok_or(format!("not valid {}", encoding.name()))
+        }
+        if bytes.contains(&0) {
+            return Ok(utf16_without_bom(bytes).and_then(|encoding| decode_as(encoding, bytes, false)))
+        }
+        Ok(decode_as(UTF_8, bytes, false).or_else(|| decode_as(WINDOWS_1252, bytes, false)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> EncodingDetector {
+        EncodingDetector::new(&[EncodingConfig {
+            files: "*.dos".to_string(),
+            encoding: "ibm866".to_string()
+        }]).unwrap()
+    }
+
+    /// Decodes then encodes a content, checking it is written back unchanged
+    fn round_trip(rpath: &str, bytes: &[u8]) -> (String, &'static str) {
+        let (text, encoding) = detector().decode(Path::new(rpath), bytes).unwrap().expect("Text file");
+        assert_eq!(encoding.encode(&text).unwrap(), bytes);
+        (text, encoding.encoding.name())
+    }
+
+    #[test]
+    fn test_detect_encodings() {
+        assert_eq!(round_trip("a.txt", "café".as_bytes()), ("café".to_string(), "UTF-8"));
+        assert_eq!(round_trip("a.txt", "\u{feff}café".as_bytes()), ("café".to_string(), "UTF-8"));
+        assert_eq!(round_trip("a.txt", b"caf\xe9 \x80"), ("café €".to_string(), "windows-1252"));
+
+        let utf16: Vec<u8> = "café".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(round_trip("a.txt", &utf16), ("café".to_string(), "UTF-16LE"));
+        let utf16: Vec<u8> = b"\xFE\xFF".iter().copied()
+            .chain("café".encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(round_trip("a.txt", &utf16), ("café".to_string(), "UTF-16BE"));
+
+        assert_eq!(round_trip("a.dos", b"\x8a\xae\xe4\xa5"), ("Кофе".to_string(), "IBM866"));
+    }
+
+    #[test]
+    fn test_binary_files() {
+        assert_eq!(detector().decode(Path::new("a.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\xff").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unencodable_text() {
+        let encoding = TextEncoding { encoding: WINDOWS_1252, bom: false };
+        assert!(encoding.encode("Кофе").is_err());
+        assert!(EncodingDetector::new(&[EncodingConfig { files: "*".to_string(), encoding: "klingon".to_string() }]).is_err());
+    }
+}
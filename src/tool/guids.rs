@@ -0,0 +1,162 @@
+use anyhow::Result;
+use globset::{Glob, GlobSetBuilder};
+use regex::Regex;
+
+use super::snapshot::read_project;
+use super::SubstitutionRule;
+
+/// Well-known GUIDs identifying project types in solutions, they must never be regenerated
+const PROJECT_TYPE_GUIDS: [&str; 15] = [
+    "00000000-0000-0000-0000-000000000000",  // Nil GUID
+    "2150e333-8fdc-42a3-9474-1a3956d46de8",  // Solution folder
+    "349c5851-65df-11da-9384-00065b846f21",  // ASP.NET web application
+    "3ac096d0-a1c2-e12c-1390-a8335801fdab",  // Test project
+    "54435603-dbb4-11d2-8724-00a0c9a8b90c",  // Setup project
+    "6ec3ee1d-3c4e-46dd-8f32-0cc8e7565705",  // F# SDK project
+    "778dae3c-4631-46ea-aa77-85c1314464d9",  // VB.NET SDK project
+    "8bc9ceb8-8b4a-11d0-8d11-00a0c91bc942",  // C++ project
+    "930c7802-8a8c-48f9-8165-68863bccd9dd",  // WiX project
+    "9a19103f-16f7-4668-be54-9a1e7a4f7556",  // C# SDK project
+    "a1591282-1198-4647-a2b1-27e5ff5f6f3b",  // Silverlight project
+    "e24c65dc-7377-472b-9aba-bc803b73c61a",  // Web site
+    "f184b08f-c81c-45f6-a57f-5abd9991f28f",  // VB.NET project
+    "f2a71f9b-5d33-465a-a702-920d77279786",  // F# project
+    "fae04ec0-301f-11d3-bf4b-00c04f79efbc",  // C# project
+];
+
+/// A GUID of the project, regenerated for every generated project
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuidVariable {
+    pub variable: String,  // Name of the private variable, e.g. `__guid_1`
+    pub value: String,  // Lowercase GUID without braces
+    pub forms: Vec<String>,  // Literal spellings found in the project, braced or uppercase
+}
+
+impl GuidVariable {
+    /// Value of the private variable in cookiecutter.json, rendered once per generated project
+    pub fn default_expression() -> String {
+        String::from("{{ uuid4() }}")
+    }
+}
+
+/// Finds the distinct GUIDs of a project
+///
+/// # Arguments
+/// * `path` - Root path of the project
+/// * `excluded_items` - List of directory or file names that are not scanned
+/// * `include` - Glob patterns of the scanned relative paths, every file when empty
+///
+/// # Returns
+/// * `Result<Vec<GuidVariable>>` - One variable per distinct GUID, whatever its case or braces,
+///   numbered in order of first appearance
+///
+/// # Errors
+/// * Returns error if the project cannot be read or a glob pattern is invalid
+pub fn find_guids(path: &str, excluded_items: &[String], include: &[String]) -> Result<Vec<GuidVariable>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in include {
+        builder.add(Glob::new(pattern)?);
+    }
+    let include_set = builder.build()?;
+
+    let guid_regex = Regex::new(
+        r"\{[0-9A-Fa-f]{8}(?:-[0-9A-Fa-f]{4}){3}-[0-9A-Fa-f]{12}\}|\b[0-9A-Fa-f]{8}(?:-[0-9A-Fa-f]{4}){3}-[0-9A-Fa-f]{12}\b"
+    ).expect("Invalid GUID regex");
+
+    let snapshot = read_project(path, excluded_items)?;
+    let mut guids: Vec<GuidVariable> = Vec::new();
+    for (rpath, content) in &snapshot.contents {
+        if !include.is_empty() && !include_set.is_match(rpath) {
+            continue
+        }
+        for form in guid_regex.find_iter(content).map(|m| m.as_str()) {
+            let value = form.trim_matches(|c| c == '{' || c == '}').to_lowercase();
+            if PROJECT_TYPE_GUIDS.contains(&value.as_str()) {
+                continue
+            }
+            match guids.iter_mut().find(|guid| guid.value == value) {
+                Some(guid) if !guid.forms.iter().any(|f| f == form) => guid.forms.push(form.to_string()),
+                Some(_) => {},
+                None => guids.push(GuidVariable {
+                    variable: format!("__guid_{}", guids.len() + 1),
+                    value,
+                    forms: vec![form.to_string()]
+                })
+            }
+        }
+    }
+    Ok(guids)
+}
+
+/// Builds the rules replacing every spelling of the GUIDs with their private variable
+///
+/// Uppercase spellings render the variable uppercased, and braces are concatenated
+/// in the Jinja expression since `{{{` would not parse.
+pub fn guid_rules(guids: &[GuidVariable]) -> Vec<SubstitutionRule> {
+    let mut rules = Vec::new();
+    for guid in guids {
+        for form in &guid.forms {
+            let pattern = regex::escape(form);
+            let uppercase = form.chars().any(|c| c.is_ascii_uppercase());
+            let expression = match uppercase {
+                true => format!("cookiecutter.{}.upper()", guid.variable),
+                false => format!("cookiecutter.{}", guid.variable)
+            };
+            let rule = match (form.starts_with('{'), uppercase) {
+                (true, _) => SubstitutionRule::raw(pattern, format!("{{{{ '{{' ~ {} ~ '}}' }}}}", expression)),
+                (false, true) => SubstitutionRule::raw(pattern, format!("{{{{ {} }}}}", expression)),
+                (false, false) => SubstitutionRule::new(pattern, guid.variable.clone())
+            };
+            rules.push(rule);
+        }
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::RegexReplacer;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const SOLUTION: &str = "Project(\"{FAE04EC0-301F-11D3-BF4B-00C04F79EFBC}\") = \"App\", \"App.csproj\", \"{8E3A9B2C-1D4F-4A6B-9C8D-7E6F5A4B3C2D}\"\n\
+        \t{8E3A9B2C-1D4F-4A6B-9C8D-7E6F5A4B3C2D}.Debug = Debug\n\
+        SolutionGuid = {0F1E2D3C-4B5A-6978-8796-A5B4C3D2E1F0}\n";
+
+    #[test]
+    fn test_find_guids() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("App.sln"), SOLUTION)?;
+        fs::write(temp_dir.path().join("AssemblyInfo.cs"), "[assembly: Guid(\"8e3a9b2c-1d4f-4a6b-9c8d-7e6f5a4b3c2d\")]")?;
+        fs::write(temp_dir.path().join("notes.txt"), "trace 11111111-2222-3333-4444-555555555555")?;
+        let path = temp_dir.path().to_str().unwrap();
+
+        let guids = find_guids(path, &[], &["*.sln".to_string(), "*.cs".to_string()])?;
+        assert_eq!(guids.len(), 2);
+        let project = guids.iter().find(|g| g.value == "8e3a9b2c-1d4f-4a6b-9c8d-7e6f5a4b3c2d").unwrap();
+        assert_eq!(project.forms.len(), 2);
+
+        assert_eq!(find_guids(path, &[], &[])?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_guid_rules() {
+        let guid = GuidVariable {
+            variable: "__guid_1".to_string(),
+            value: "8e3a9b2c-1d4f-4a6b-9c8d-7e6f5a4b3c2d".to_string(),
+            forms: vec![
+                "{8E3A9B2C-1D4F-4A6B-9C8D-7E6F5A4B3C2D}".to_string(),
+                "8E3A9B2C-1D4F-4A6B-9C8D-7E6F5A4B3C2D".to_string(),
+                "8e3a9b2c-1d4f-4a6b-9c8d-7e6f5a4b3c2d".to_string(),
+            ]
+        };
+        let replacer = RegexReplacer::new(guid_rules(&[guid]));
+
+        assert_eq!(
+            replacer.replace("\"{8E3A9B2C-1D4F-4A6B-9C8D-7E6F5A4B3C2D}\" 8E3A9B2C-1D4F-4A6B-9C8D-7E6F5A4B3C2D 8e3a9b2c-1d4f-4a6b-9c8d-7e6f5a4b3c2d"),
+            "\"{{ '{' ~ cookiecutter.__guid_1.upper() ~ '}' }}\" {{ cookiecutter.__guid_1.upper() }} {{cookiecutter.__guid_1}}"
+        );
+    }
+}
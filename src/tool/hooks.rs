@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::Path;
+
+use super::cuttercookie_json::ConditionalPathConfig;
+use super::directives::{condition_variables, translate_condition};
+use super::process_files::template_path;
+use super::{CookiecutterConfig, RegexReplacer};
+
+/// A path of the generated project that only exists when a condition holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalPath {
+    pub path: String,  // Templatized path, relative to the generated project root
+    pub condition: String,  // Jinja expression keeping the path when true
+}
+
+/// Renders a `post_gen_project.py` hook removing the conditional paths whose condition is false
+///
+/// Each path is wrapped in a Jinja `{% if not <condition> %}` block, so once cookiecutter has
+/// rendered the hook, the removal list only holds the paths that must disappear.
+///
+/// # Arguments
+/// * `paths` - Conditional paths, a path nested in an already listed directory is skipped
+///
+/// # Returns
+/// * The python source of the hook
+pub fn render_post_gen_hook(paths: &[ConditionalPath]) -> String {
+    let mut hook = String::from(
+        "\"\"\"Removes the optional paths whose condition is false, generated by cuttercookie\"\"\"\n\
+         import os\n\
+         import shutil\n\
+         \n\
+         REMOVE_PATHS = [\n"
+    );
+
+    let mut listed: Vec<&ConditionalPath> = Vec::new();
+    for path in paths {
+        let nested = listed.iter().any(|parent| {
+            parent.condition == path.condition && Path::new(&path.path).starts_with(&parent.path)
+        });
+        if nested {
+            continue
+        }
+        let literal = path.path.replace('\\', "\\\\").replace('"', "\\\"");
+        hook.push_str(&format!("    {{% if not ({}) %}}\"{}\",{{% endif %}}\n", path.condition, literal));
+        listed.push(path);
+    }
+
+    hook.push_str(
+        "]\n\
+         \n\
+         for path in REMOVE_PATHS:\n\
+         \x20   if os.path.isdir(path) and not os.path.islink(path):\n\
+         \x20       shutil.rmtree(path)\n\
+         \x20   elif os.path.lexists(path):\n\
+         \x20       os.remove(path)\n"
+    );
+    hook
+}
+
+/// Resolves the conditional paths declared in `cuttercookie.json` against the source project
+///
+/// # Arguments
+/// * `source_path` - Root path of the project to templatize
+/// * `declared` - Conditional paths of the configuration, relative to the project root
+/// * `replacer` - RegexReplacer templatizing the paths
+/// * `cookiecutter` - Variables of the template
+///
+/// # Returns
+/// * `Result<Vec<ConditionalPath>, String>` - Templatized paths with Jinja conditions
+///
+/// # Errors
+/// * Returns error if a declared path does not exist in the source project
+/// * Returns error if a condition reads a variable that is neither a boolean nor a choice
+pub fn resolve_conditional_paths(
+    source_path: &Path,
+    declared: &[ConditionalPathConfig],
+    replacer: &RegexReplacer,
+    cookiecutter: &CookiecutterConfig
+) -> Result<Vec<ConditionalPath>, String> {
+    declared.iter()
+        .map(|entry| {
+            if !source_path.join(&entry.path).exists() {
+                return Err(format!("Conditional path \"{}\" not found in the project", entry.path))
+            }
+            for variable in condition_variables(&entry.condition) {
+                match cookiecutter.get(&variable) {
+                    Some(value) if value.is_boolean() || value.is_array() => (),
+                    _ => return Err(format!(
+                        "Conditional path \"{}\": \"{}\" must be a boolean or choice variable of cookiecutter.json",
+                        entry.path, variable
+                    ))
+                }
+            }
+            Ok(ConditionalPath {
+                path: template_path(Path::new(&entry.path), replacer, &mut Vec::new())?,
+                condition: translate_condition(&entry.condition)
+            })
+        })
+        .collect()
+}
+
+/// Writes the post generation hook into the `hooks` directory of the template
+///
+/// # Arguments
+/// * `template_root` - Directory holding the template `cookiecutter.json`
+/// * `paths` - Conditional paths removed by the hook
+///
+/// # Errors
+/// * Returns error if a hook already exists or cannot be written
+pub fn write_post_gen_hook(template_root: &Path, paths: &[ConditionalPath]) -> Result<(), String> {
+    let hooks_dir = template_root.join("hooks");
+    let hook_path = hooks_dir.join("post_gen_project.py");
+    if hook_path.exists() {
+        return Err(format!("\"{}\" already exists", hook_path.display()))
+    }
+    fs::create_dir_all(&hooks_dir).map_err(|err| err.to_string())?;
+    fs::write(&hook_path, render_post_gen_hook(paths)).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditional(path: &str, condition: &str) -> ConditionalPath {
+        ConditionalPath { path: path.to_string(), condition: condition.to_string() }
+    }
+
+    #[test]
+    fn test_hook_lists_conditional_paths() {
+        let hook = render_post_gen_hook(&[
+            conditional("docker", "cookiecutter.use_docker"),
+            conditional("docker/Dockerfile", "cookiecutter.use_docker"),
+            conditional("run_local.sh", "not cookiecutter.use_docker")
+        ]);
+
+        assert!(hook.contains("    {% if not (cookiecutter.use_docker) %}\"docker\",{% endif %}\n"));
+        assert!(hook.contains("    {% if not (not cookiecutter.use_docker) %}\"run_local.sh\",{% endif %}\n"));
+        assert!(!hook.contains("Dockerfile"));
+        assert!(hook.contains("shutil.rmtree(path)"));
+    }
+
+    #[test]
+    fn test_resolve_conditional_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("demo_ci")).unwrap();
+        let replacer = RegexReplacer::new(vec![
+            super::super::SubstitutionRule::new("demo".to_string(), "name".to_string())
+        ]);
+        let mut cookiecutter = CookiecutterConfig::new();
+        cookiecutter.insert("name", serde_json::json!("demo"));
+        cookiecutter.insert("ci", serde_json::json!(["github", "gitlab"]));
+        let declare = |path: &str, condition: &str| ConditionalPathConfig {
+            path: path.to_string(),
+            condition: condition.to_string()
+        };
+
+        let resolved = resolve_conditional_paths(
+            temp_dir.path(), &[declare("demo_ci", "ci == 'gitlab'")], &replacer, &cookiecutter
+        ).unwrap();
+        assert_eq!(resolved, vec![conditional("{{cookiecutter.name}}_ci", "cookiecutter.ci == 'gitlab'")]);
+
+        let missing = resolve_conditional_paths(temp_dir.path(), &[declare("other", "ci == 'gitlab'")], &replacer, &cookiecutter);
+        assert!(missing.unwrap_err().contains("not found"));
+        let not_boolean = resolve_conditional_paths(temp_dir.path(), &[declare("demo_ci", "name")], &replacer, &cookiecutter);
+        assert!(not_boolean.unwrap_err().contains("boolean or choice"));
+    }
+}
@@ -0,0 +1,367 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Result;
+use regex::Regex;
+
+use super::diff::{diff, DiffOp};
+use super::snapshot::{read_project, ProjectSnapshot};
+use super::suggest::to_variable_name;
+use super::{RegexReplacer, SubstitutionRule};
+
+/// Equal tokens across which two neighbouring changes are merged into a single value
+const VALUE_SEPARATORS: [&str; 3] = [" ", "-", "."];
+
+/// Minimum line similarity for two files with different paths to be aligned
+const MIN_ALIGNMENT_SIMILARITY: f64 = 0.5;
+
+/// A variable inferred from the strings that differ between two instances of a project
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredVariable {
+    pub variable: String,  // The proposed cookiecutter variable name
+    pub first: String,  // Value found in the first instance, used as default
+    pub second: String,  // Value found in the second instance
+    pub occurrences: usize,  // How many aligned differences showed this pair of values
+}
+
+/// Counts pairs of differing values, remembering the order they were first seen in
+#[derive(Default)]
+struct PairCounter {
+    pairs: Vec<((String, String), usize)>,
+    index: HashMap<(String, String), usize>
+}
+
+impl PairCounter {
+    /// Records every value pair differing between two strings
+    fn add_diff(&mut self, a: &str, b: &str) {
+        for pair in varying_values(a, b) {
+            match self.index.get(&pair) {
+                Some(&i) => self.pairs[i].1 += 1,
+                None => {
+                    self.index.insert(pair.clone(), self.pairs.len());
+                    self.pairs.push((pair, 1));
+                }
+            }
+        }
+    }
+}
+
+/// Splits a string into word, whitespace and punctuation tokens
+fn tokenize(s: &str) -> Vec<&str> {
+    let re = Regex::new(r"\w+|\s+|[^\w\s]").expect("Invalid token regex");
+    re.find_iter(s).map(|m| m.as_str()).collect()
+}
+
+/// Returns the pairs of values that differ between two strings, at the token level
+///
+/// Changes only separated by a single `VALUE_SEPARATORS` token are merged, so
+/// `Acme Widget` against `Blue Gadget` yields one pair instead of two.
+fn varying_values(a: &str, b: &str) -> Vec<(String, String)> {
+    let (ta, tb) = (tokenize(a), tokenize(b));
+    let ops = diff(&ta, &tb);
+
+    let mut values: Vec<(String, String)> = Vec::new();
+    let mut merge_next = false;
+    for (i, op) in ops.iter().enumerate() {
+        match op {
+            DiffOp::Equal { a: ra, .. } => {
+                let separator = ra.len() == 1 && VALUE_SEPARATORS.contains(&ta[ra.start]);
+                let between_changes = i > 0 && i + 1 < ops.len();
+                merge_next = separator && between_changes;
+                if merge_next {
+                    if let Some(last) = values.last_mut() {
+                        last.0.push_str(ta[ra.start]);
+                        last.1.push_str(ta[ra.start]);
+                    }
+                }
+            },
+            DiffOp::Change { a: ra, b: rb } => {
+                let (va, vb) = (ta[ra.clone()].concat(), tb[rb.clone()].concat());
+                match (merge_next, values.last_mut()) {
+                    (true, Some(last)) => {
+                        last.0.push_str(&va);
+                        last.1.push_str(&vb);
+                    },
+                    _ => values.push((va, vb))
+                }
+                merge_next = false;
+            }
+        }
+    }
+    values
+}
+
+/// Tells if a value pair can become a variable
+///
+/// Insertions, deletions, multi-line values and short or letter-free values
+/// (version digits, punctuation) are too ambiguous to be templatized.
+fn is_variable_value(first: &str, second: &str) -> bool {
+    [first, second].iter().all(|v| {
+        v.trim() == *v
+            && v.chars().count() >= 3
+            && !v.contains('\n')
+            && v.chars().any(char::is_alphabetic)
+    })
+}
+
+/// Returns the line similarity of two texts, between 0 and 1
+fn similarity(a: &str, b: &str) -> f64 {
+    let (la, lb): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    if la.is_empty() && lb.is_empty() {
+        return 1.0
+    }
+    let equal: usize = diff(&la, &lb).iter()
+        .map(|op| match op {
+            DiffOp::Equal { a, .. } => a.len(),
+            DiffOp::Change { .. } => 0
+        })
+        .sum();
+    2.0 * equal as f64 / (la.len() + lb.len()) as f64
+}
+
+/// Aligns the relative paths of two projects
+///
+/// Identical paths are aligned first. Each remaining path of the first project is then
+/// aligned with a remaining path of the second one with the same depth and extension,
+/// either because known value pairs turn one into the other, or because their
+/// contents are similar enough.
+fn align_paths(first: &ProjectSnapshot, second: &ProjectSnapshot, known: &[(String, String)]) -> Vec<(String, String)> {
+    let second_paths: HashSet<&str> = second.paths.iter().map(String::as_str).collect();
+    let mut aligned: Vec<(String, String)> = Vec::new();
+    let mut remaining_first = Vec::new();
+    for rpath in &first.paths {
+        if second_paths.contains(rpath.as_str()) {
+            aligned.push((rpath.clone(), rpath.clone()));
+        } else {
+            remaining_first.push(rpath);
+        }
+    }
+    let first_paths: HashSet<&str> = first.paths.iter().map(String::as_str).collect();
+    let mut remaining_second: Vec<&String> = second.paths.iter()
+        .filter(|rpath| !first_paths.contains(rpath.as_str()))
+        .collect();
+
+    let shape = |rpath: &str| {
+        let path = Path::new(rpath);
+        (path.components().count(), path.extension().map(|e| e.to_os_string()))
+    };
+
+    for rpath in remaining_first {
+        let translated = known.iter()
+            .fold(rpath.clone(), |path, (a, b)| path.replace(a.as_str(), b));
+        let candidates = remaining_second.iter()
+            .enumerate()
+            .filter(|(_, other)| shape(other) == shape(rpath));
+
+        let mut best: Option<(usize, f64)> = None;
+        for (i, other) in candidates {
+            let score = if **other == translated {
+                f64::INFINITY
+            } else {
+                match (first.content(rpath), second.content(other)) {
+                    (Some(a), Some(b)) => similarity(a, b),
+                    _ => 0.0
+                }
+            };
+            if score >= MIN_ALIGNMENT_SIMILARITY && best.is_none_or(|(_, s)| score > s) {
+                best = Some((i, score));
+            }
+        }
+        if let Some((i, _)) = best {
+            aligned.push((rpath.clone(), remaining_second.remove(i).clone()));
+        }
+    }
+    aligned
+}
+
+/// Records the differences between the contents of two aligned files
+///
+/// Changed line blocks of the same length are compared line by line, other blocks as a whole.
+fn add_content_diff(counter: &mut PairCounter, a: &str, b: &str) {
+    let (la, lb): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    for op in diff(&la, &lb) {
+        if let DiffOp::Change { a: ra, b: rb } = op {
+            if ra.len() == rb.len() {
+                for (line_a, line_b) in la[ra].iter().zip(&lb[rb]) {
+                    counter.add_diff(line_a, line_b);
+                }
+            } else if !ra.is_empty() && !rb.is_empty() {
+                counter.add_diff(&la[ra].join("\n"), &lb[rb].join("\n"));
+            }
+        }
+    }
+}
+
+/// Infers variables from the strings differing between two instances of the same project
+///
+/// # Arguments
+/// * `first` - Root path of the first instance, whose values become the defaults
+/// * `second` - Root path of the second instance
+/// * `excluded_items` - List of directory or file names that are not compared
+/// * `min_occurrences` - Minimum number of differences showing a pair for it to be kept
+///
+/// # Returns
+/// * `Result<Vec<InferredVariable>>` - Variables sorted by decreasing occurrences
+///
+/// # Behavior
+/// * The root directory names, the aligned relative paths and the aligned file contents are diffed
+/// * A value is bound to a single variable: when the same value differs in several
+///   ways, the most frequent pair wins
+pub fn infer_variables(first: &str, second: &str, excluded_items: &[String], min_occurrences: usize) -> Result<Vec<InferredVariable>> {
+    let first_snapshot = read_project(first, excluded_items)?;
+    let second_snapshot = read_project(second, excluded_items)?;
+
+    // Root names and contents of identical paths give the first pairs
+    let mut counter = PairCounter::default();
+    let root_name = |path: &str| Path::new(path)
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+    counter.add_diff(&root_name(first), &root_name(second));
+    for (rpath, content) in &first_snapshot.contents {
+        if let Some(other) = second_snapshot.content(rpath) {
+            add_content_diff(&mut counter, content, other);
+        }
+    }
+
+    // Those pairs help aligning renamed paths, whose names and contents are then compared
+    let known: Vec<(String, String)> = counter.pairs.iter()
+        .filter(|((a, b), _)| is_variable_value(a, b))
+        .map(|(pair, _)| pair.clone())
+        .collect();
+    for (path_a, path_b) in align_paths(&first_snapshot, &second_snapshot, &known) {
+        if path_a == path_b {
+            continue
+        }
+        counter.add_diff(&path_a, &path_b);
+        if let (Some(a), Some(b)) = (first_snapshot.content(&path_a), second_snapshot.content(&path_b)) {
+            add_content_diff(&mut counter, a, b);
+        }
+    }
+
+    // Keep the most frequent consistent pairs
+    let mut pairs = counter.pairs;
+    pairs.sort_by_key(|(_, occurrences)| Reverse(*occurrences));
+    let mut used_values: HashSet<String> = HashSet::new();
+    let mut variables: Vec<InferredVariable> = Vec::new();
+    for ((first, second), occurrences) in pairs {
+        if occurrences < min_occurrences
+            || !is_variable_value(&first, &second)
+            || used_values.contains(&first)
+            || used_values.contains(&second)
+        {
+            continue
+        }
+
+        let base = to_variable_name(&first);
+        let mut variable = base.clone();
+        let mut suffix = 2;
+        while variables.iter().any(|v| v.variable == variable) {
+            variable = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+
+        used_values.insert(first.clone());
+        used_values.insert(second.clone());
+        variables.push(InferredVariable { variable, first, second, occurrences });
+    }
+    Ok(variables)
+}
+
+/// Builds a replacer templatizing the first instance values of inferred variables
+pub fn build_inferred_replacer(variables: &[InferredVariable]) -> RegexReplacer {
+    let rules = variables.iter()
+        .map(|v| SubstitutionRule::new(regex::escape(&v.first), v.variable.clone()))
+        .collect();
+    RegexReplacer::new(rules)
+}
+
+/// Renders inferred variables as a table showing both values
+pub fn render_inferred(variables: &[InferredVariable]) -> String {
+    let mut rendered = format!("{:<24} {:>6}  {:<24} {}\n", "variable", "count", "first", "second");
+    for v in variables {
+        let (first, second) = (format!("{:?}", v.first), format!("{:?}", v.second));
+        rendered.push_str(&format!("{:<24} {:>6}  {:<24} {}\n", v.variable, v.occurrences, first, second));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Creates a project named `name` with the given files in a temporary directory
+    fn setup_project(name: &str, files: &[(&str, &str)]) -> (TempDir, String) {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let root = temp_dir.path().join(name);
+        for (file_path, content) in files {
+            let full_path = root.join(file_path);
+            fs::create_dir_all(full_path.parent().unwrap()).expect("Failed to create parent directory");
+            fs::write(full_path, content).expect("Failed to write test file");
+        }
+        let root = root.to_string_lossy().into_owned();
+        (temp_dir, root)
+    }
+
+    #[test]
+    fn test_varying_values_merges_separators() {
+        assert_eq!(
+            varying_values("name = \"Acme Widget\"", "name = \"Blue Gadget\""),
+            vec![("Acme Widget".to_string(), "Blue Gadget".to_string())]
+        );
+        assert_eq!(
+            varying_values("use acme_widget::run;", "use blue_gadget::run;"),
+            vec![("acme_widget".to_string(), "blue_gadget".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_is_variable_value() {
+        assert!(is_variable_value("acme", "blue"));
+        assert!(!is_variable_value("1", "2"));
+        assert!(!is_variable_value("", "blue"));
+        assert!(!is_variable_value("a\nb", "blue"));
+    }
+
+    #[test]
+    fn test_infer_from_contents_and_paths() {
+        let (_first_dir, first) = setup_project("acme_widget", &[
+            ("Cargo.toml", "[package]\nname = \"acme_widget\"\nversion = \"0.1.0\"\nauthors = [\"Jane Doe\"]\n"),
+            ("src/acme_widget.rs", "pub fn run() {}\n// part of acme_widget\n"),
+            ("README.md", "# Acme Widget\nWritten by Jane Doe\n")
+        ]);
+        let (_second_dir, second) = setup_project("blue_gadget", &[
+            ("Cargo.toml", "[package]\nname = \"blue_gadget\"\nversion = \"0.2.0\"\nauthors = [\"John Roe\"]\n"),
+            ("src/blue_gadget.rs", "pub fn run() {}\n// part of blue_gadget\n"),
+            ("README.md", "# Blue Gadget\nWritten by John Roe\n")
+        ]);
+
+        let variables = infer_variables(&first, &second, &[], 1).unwrap();
+
+        let find = |name: &str| variables.iter()
+            .find(|v| v.variable == name)
+            .unwrap_or_else(|| panic!("Missing variable {}", name));
+        let project = find("acme_widget");
+        assert_eq!(project.second, "blue_gadget");
+        assert_eq!(project.occurrences, 4);
+        assert_eq!(find("acme_widget_2").first, "Acme Widget");
+        assert_eq!(find("jane_doe").second, "John Roe");
+        assert!(variables.iter().all(|v| v.first != "1"));
+    }
+
+    #[test]
+    fn test_build_inferred_replacer_escapes_values() {
+        let replacer = build_inferred_replacer(&[InferredVariable {
+            variable: "domain".to_string(),
+            first: "acme.io".to_string(),
+            second: "blue.io".to_string(),
+            occurrences: 1
+        }]);
+
+        assert_eq!(replacer.replace("acme.io acmexio"), "{{cookiecutter.domain}} acmexio");
+    }
+}
@@ -0,0 +1,337 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use regex::Regex;
+use toml_edit::DocumentMut;
+
+use super::segment::{replace_in_lines, Segment};
+
+/// Name of the choice variable selecting the license of generated projects
+pub const LICENSE_VARIABLE: &str = "open_source_license";
+
+/// File names recognised as the license of a project, at its root
+const LICENSE_FILES: [&str; 6] = ["LICENSE", "LICENSE.md", "LICENSE.txt", "LICENCE", "LICENCE.md", "COPYING"];
+
+/// Manifests whose `license` field holds the SPDX identifier of the project
+const MANIFEST_FILES: [&str; 3] = ["Cargo.toml", "package.json", "pyproject.toml"];
+
+/// Copyright line used when the project license has none
+const DEFAULT_NOTICE: &str = "Copyright (c) [year] [fullname]\n";
+
+/// A license text shipped with cuttercookie
+struct LicenseText {
+    id: &'static str,  // SPDX identifier
+    text: &'static str,  // Body of the license
+    notice: bool,  // The body is preceded by the copyright line of the project
+}
+
+/// Licenses offered as choices besides the one of the project
+const LICENSE_TEXTS: [LicenseText; 5] = [
+    LicenseText { id: "MIT", text: include_str!("licenses/MIT.txt"), notice: true },
+    LicenseText { id: "Apache-2.0", text: include_str!("licenses/Apache-2.0.txt"), notice: false },
+    LicenseText { id: "BSD-3-Clause", text: include_str!("licenses/BSD-3-Clause.txt"), notice: true },
+    LicenseText { id: "ISC", text: include_str!("licenses/ISC.txt"), notice: true },
+    LicenseText { id: "Unlicense", text: include_str!("licenses/Unlicense.txt"), notice: false },
+];
+
+/// Sentences identifying a license text, whitespace being normalised
+///
+/// The GPLv3 text always mentions "any later version" in its appendix, whether the
+/// project allows later versions or not: only the manifest or an SPDX header tell.
+const FINGERPRINTS: [(&str, &[&str]); 8] = [
+    ("Apache-2.0", &["Apache License", "Version 2.0"]),
+    ("MIT", &["Permission is hereby granted, free of charge"]),
+    ("BSD-3-Clause", &["Redistribution and use in source and binary forms", "Neither the name"]),
+    ("BSD-2-Clause", &["Redistribution and use in source and binary forms"]),
+    ("ISC", &["Permission to use, copy, modify, and/or distribute this software for any purpose"]),
+    ("Unlicense", &["This is free and unencumbered software released into the public domain"]),
+    ("GPL-3.0-only", &["GNU GENERAL PUBLIC LICENSE", "Version 3"]),
+    ("MPL-2.0", &["Mozilla Public License Version 2.0"]),
+];
+
+/// Returns the SPDX identifier of a license text
+///
+/// An explicit `SPDX-License-Identifier` wins over the fingerprints of the known licenses.
+pub fn identify_license(content: &str) -> Option<String> {
+    let spdx_regex = Regex::new(r"SPDX-License-Identifier:\s*([\w.+-]+)").expect("Invalid SPDX regex");
+    if let Some(caps) = spdx_regex.captures(content) {
+        return Some(caps[1].to_string())
+    }
+    let normalised = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    FINGERPRINTS.iter()
+        .find(|(_, sentences)| sentences.iter().all(|sentence| normalised.contains(sentence)))
+        .map(|(id, _)| id.to_string())
+}
+
+/// The license file of a project and its identification
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectLicense {
+    pub file_name: String,  // Name of the license file at the project root
+    pub id: String,  // SPDX identifier of the license
+    pub content: String,  // Original license text
+}
+
+/// Reads the `license` field of the manifests at the root of a project
+///
+/// Manifests that cannot be read or parsed are skipped, the license text then
+/// identifying the license.
+///
+/// # Returns
+/// * `Option<String>` - The SPDX identifier of the first manifest declaring one
+fn manifest_license(path: &str) -> Option<String> {
+    MANIFEST_FILES.iter().find_map(|manifest| {
+        let content = fs::read_to_string(Path::new(path).join(manifest)).ok()?;
+        let license = match *manifest {
+            "package.json" => serde_json::from_str::<serde_json::Value>(&content).ok()?["license"].as_str()?.to_string(),
+            _ => {
+                let doc: DocumentMut = content.parse().ok()?;
+                let poetry = doc.get("tool").and_then(|tool| tool.get("poetry"));
+                let license = [doc.get("package"), doc.get("project"), poetry].into_iter()
+                    .flatten()
+                    .find_map(|table| table.get("license")?.as_str())?
+                    .to_string();
+                license
+            }
+        };
+        Some(license)
+    })
+}
+
+/// Finds and identifies the license file at the root of a project
+///
+/// The `license` field of the manifests wins, the license text being only
+/// identified when no manifest declares one.
+///
+/// # Returns
+/// * `Result<Option<ProjectLicense>>` - The license, or `None` if no license file is recognised
+///
+/// # Errors
+/// * Returns error if the license file cannot be read
+pub fn find_project_license(path: &str) -> Result<Option<ProjectLicense>> {
+    let declared = manifest_license(path);
+    for file_name in LICENSE_FILES {
+        let file_path = Path::new(path).join(file_name);
+        if !file_path.is_file() {
+            continue
+        }
+        let content = fs::read_to_string(&file_path)?;
+        if let Some(id) = declared.clone().or_else(|| identify_license(&content)) {
+            return Ok(Some(ProjectLicense { file_name: file_name.to_string(), id, content }))
+        }
+    }
+    Ok(None)
+}
+
+/// Tells whether a file is a manifest holding a `license` field
+fn is_manifest(rpath: &Path) -> bool {
+    rpath.file_name()
+        .is_some_and(|name| MANIFEST_FILES.iter().any(|manifest| name == *manifest))
+}
+
+/// Replaces the license of a project with the one chosen at generation time
+#[derive(Debug, Clone)]
+pub struct LicenseTemplater {
+    license: ProjectLicense,  // License of the source project, first choice
+    choices: Vec<String>,  // SPDX identifiers offered, the project one included
+}
+
+impl LicenseTemplater {
+    /// Creates a templater offering the project license and the requested bundled ones
+    ///
+    /// # Arguments
+    /// * `license` - License of the source project
+    /// * `choices` - Bundled licenses offered besides the project one, every bundled one when empty
+    ///
+    /// # Errors
+    /// * Returns error if a choice is not a bundled license
+    pub fn new(license: ProjectLicense, choices: &[String]) -> Result<Self, String> {
+        let requested: Vec<String> = match choices.is_empty() {
+            true => LICENSE_TEXTS.iter().map(|text| text.id.to_string()).collect(),
+            false => choices.to_vec()
+        };
+        let mut all_choices = vec![license.id.clone()];
+        for choice in requested {
+            if choice != license.id && !LICENSE_TEXTS.iter().any(|text| text.id == choice) {
+                return Err(format!(
+                    "Unknown license \"{}\", expected one of: {}",
+                    choice,
+                    LICENSE_TEXTS.iter().map(|text| text.id).collect::<Vec<_>>().join(", ")
+                ))
+            }
+            if !all_choices.contains(&choice) {
+                all_choices.push(choice);
+            }
+        }
+        Ok(Self { license, choices: all_choices })
+    }
+
+    /// SPDX identifiers offered by the choice variable, the project license first
+    pub fn choices(&self) -> &[String] {
+        &self.choices
+    }
+
+    /// Tells whether a file is the license file or a manifest, which `apply` may change
+    pub fn targets(&self, rpath: &Path) -> bool {
+        rpath == Path::new(&self.license.file_name) || is_manifest(rpath)
+    }
+
+    /// Templatizes the license file and the `license` fields of manifests
+    ///
+    /// # Arguments
+    /// * `rpath` - Relative path of the file
+    /// * `segments` - Segments of the file content
+    ///
+    /// # Returns
+    /// * For the license file, an `{% if %}` chain holding one body per choice
+    /// * For manifests, the segments with the project SPDX identifier templated
+    /// * Otherwise the segments unchanged
+    pub fn apply(&self, rpath: &Path, segments: Vec<Segment>) -> Vec<Segment> {
+        if rpath == Path::new(&self.license.file_name) {
+            return self.license_block()
+        }
+        if !is_manifest(rpath) {
+            return segments
+        }
+
+        let field_regex = Regex::new(r#"^\s*"?license"?\s*[:=]"#).expect("Invalid license field regex");
+        let quoted_id = [format!("\"{}\"", self.license.id), format!("'{}'", self.license.id)];
+        replace_in_lines(segments, |line| {
+            if !field_regex.is_match(line) {
+                return Vec::new()
+            }
+            quoted_id.iter()
+                .filter_map(|quoted| line.find(quoted.as_str()))
+                .take(1)
+                .map(|start| {
+                    let id_start = start + 1;
+                    (id_start..id_start + self.license.id.len(), format!("{{{{cookiecutter.{}}}}}", LICENSE_VARIABLE))
+                })
+                .collect()
+        })
+    }
+
+    /// Builds the `{% if %}` chain of the license file
+    ///
+    /// The project license keeps its original text, whose copyright line also
+    /// precedes the bundled bodies that expect one.
+    fn license_block(&self) -> Vec<Segment> {
+        let notice = self.license.content.lines()
+            .find(|line| line.trim_start().to_lowercase().starts_with("copyright"))
+            .map(|line| format!("{}\n", line))
+            .unwrap_or_else(|| DEFAULT_NOTICE.to_string());
+
+        let mut segments = Vec::new();
+        for (index, choice) in self.choices.iter().enumerate() {
+            let keyword = if index == 0 { "if" } else { "elif" };
+            segments.push(Segment::Literal(format!(
+                "{{% {} cookiecutter.{} == '{}' %}}", keyword, LICENSE_VARIABLE, choice
+            )));
+            if *choice == self.license.id {
+                segments.push(Segment::Text(self.license.content.clone()));
+                continue
+            }
+            let text = LICENSE_TEXTS.iter()
+                .find(|text| text.id == choice)
+                .expect("Choices are validated against the bundled licenses");
+            if text.notice {
+                segments.push(Segment::Text(notice.clone()));
+                segments.push(Segment::Literal("\n".to_string()));
+            }
+            segments.push(Segment::Literal(text.text.to_string()));
+        }
+        segments.push(Segment::Literal("{% endif %}".to_string()));
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project_license() -> ProjectLicense {
+        ProjectLicense {
+            file_name: "LICENSE".to_string(),
+            id: "MIT".to_string(),
+            content: format!("Copyright (c) 2024 Acme\n\n{}", LICENSE_TEXTS[0].text)
+        }
+    }
+
+    #[test]
+    fn test_identify_license() {
+        for text in &LICENSE_TEXTS {
+            assert_eq!(identify_license(text.text).as_deref(), Some(text.id));
+        }
+        assert_eq!(identify_license("// SPDX-License-Identifier: EUPL-1.2\n").as_deref(), Some("EUPL-1.2"));
+        assert_eq!(identify_license("All rights reserved.\n"), None);
+    }
+
+    #[test]
+    fn test_find_project_license() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().to_str().unwrap();
+        assert_eq!(find_project_license(path)?, None);
+
+        fs::write(temp_dir.path().join("LICENSE.md"), project_license().content)?;
+        let license = find_project_license(path)?.unwrap();
+        assert_eq!((license.file_name.as_str(), license.id.as_str()), ("LICENSE.md", "MIT"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_license_wins() -> Result<()> {
+        let gpl = "GNU GENERAL PUBLIC LICENSE\nVersion 3, 29 June 2007\n\n\
+            either version 3 of the License, or (at your option) any later version.\n";
+        assert_eq!(identify_license(gpl).as_deref(), Some("GPL-3.0-only"));
+
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("COPYING"), gpl)?;
+        assert_eq!(find_project_license(path)?.unwrap().id, "GPL-3.0-only");
+
+        fs::write(temp_dir.path().join("pyproject.toml"), "[project]\nname = \"demo\"\nlicense = \"GPL-3.0-or-later\"\n")?;
+        assert_eq!(find_project_license(path)?.unwrap().id, "GPL-3.0-or-later");
+
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package\nlicense = \"MIT\"\n")?;
+        assert_eq!(find_project_license(path)?.unwrap().id, "GPL-3.0-or-later");
+
+        fs::write(temp_dir.path().join("package.json"), "{\"license\": \"MIT\"}")?;
+        assert_eq!(find_project_license(path)?.unwrap().id, "MIT");
+
+        fs::remove_file(temp_dir.path().join("pyproject.toml"))?;
+        fs::write(temp_dir.path().join("package.json"), "{\"license\": ")?;
+        assert_eq!(find_project_license(path)?.unwrap().id, "GPL-3.0-only");
+        Ok(())
+    }
+
+    #[test]
+    fn test_license_block() {
+        let templater = LicenseTemplater::new(project_license(), &["ISC".to_string(), "MIT".to_string()]).unwrap();
+        assert_eq!(templater.choices(), ["MIT".to_string(), "ISC".to_string()]);
+
+        let segments = templater.apply(Path::new("LICENSE"), Vec::new());
+        assert_eq!(segments[0], Segment::Literal("{% if cookiecutter.open_source_license == 'MIT' %}".to_string()));
+        assert_eq!(segments[1], Segment::Text(project_license().content));
+        assert_eq!(segments[2], Segment::Literal("{% elif cookiecutter.open_source_license == 'ISC' %}".to_string()));
+        assert_eq!(segments[3], Segment::Text("Copyright (c) 2024 Acme\n".to_string()));
+        assert_eq!(segments.last(), Some(&Segment::Literal("{% endif %}".to_string())));
+
+        assert!(LicenseTemplater::new(project_license(), &["WTFPL".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_manifest_license_field() {
+        let templater = LicenseTemplater::new(project_license(), &[]).unwrap();
+        let segments = templater.apply(
+            Path::new("Cargo.toml"),
+            vec![Segment::Text("name = \"MIT\"\nlicense = \"MIT\"\n".to_string())]
+        );
+
+        assert_eq!(segments, vec![
+            Segment::Text("name = \"MIT\"\nlicense = \"".to_string()),
+            Segment::Literal("{{cookiecutter.open_source_license}}".to_string()),
+            Segment::Text("\"\n".to_string()),
+        ]);
+    }
+}
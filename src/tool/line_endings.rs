@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use serde::Deserialize;
+
+/// Byte-order mark of UTF-8 files, kept out of the processed text and written back
+pub const BOM: &str = "\u{feff}";
+
+/// Splits the byte-order mark off a content
+///
+/// # Returns
+/// * The byte-order mark, empty when the content has none, and the text following it
+pub fn split_bom(content: &str) -> (&'static str, &str) {
+    match content.strip_prefix(BOM) {
+        Some(text) => (BOM, text),
+        None => ("", content)
+    }
+}
+
+/// Line endings of the templatized files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    #[default]
+    Preserve,  // Every file keeps its own line endings
+    Lf,  // Every line ends with `\n`
+    Crlf,  // Every line ends with `\r\n`
+}
+
+impl LineEndings {
+    /// Returns the line ending every file is normalised to, `None` when preserved
+    ///
+    /// Also the `_new_lines` setting making cookiecutter generate the same line endings.
+    pub fn new_line(&self) -> Option<&'static str> {
+        match self {
+            LineEndings::Preserve => None,
+            LineEndings::Lf => Some("\n"),
+            LineEndings::Crlf => Some("\r\n")
+        }
+    }
+
+    /// Normalises the line endings of a text, lone carriage returns being kept
+    ///
+    /// # Returns
+    /// * The text, borrowed when its line endings already conform
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let conforms = match self {
+            LineEndings::Preserve => true,
+            LineEndings::Lf => !text.contains("\r\n"),
+            LineEndings::Crlf => text.match_indices('\n').all(|(index, _)| text[..index].ends_with('\r'))
+        };
+        if conforms {
+            return Cow::Borrowed(text)
+        }
+        let mut writer = NewLineWriter::new(Vec::with_capacity(text.len()), *self);
+        writer.write_all(text.as_bytes()).expect("Writing to memory cannot fail");
+        let normalised = writer.finish().expect("Writing to memory cannot fail");
+        Cow::Owned(String::from_utf8(normalised).expect("Line endings are ASCII"))
+    }
+}
+
+/// Normalises the line endings of the text written through it
+///
+/// A carriage return ending a write waits for the next one, in case it starts
+/// with the line feed completing a `\r\n`, so `finish` must end the text.
+pub struct NewLineWriter<W: Write> {
+    inner: W,
+    new_line: Option<&'static str>,  // Line ending written, `None` to write the text unchanged
+    pending_cr: bool,  // The last byte written is a carriage return not written yet
+}
+
+impl<W: Write> NewLineWriter<W> {
+    /// Creates a writer normalising to the line endings of the options
+    pub fn new(inner: W, line_endings: LineEndings) -> Self {
+        Self { inner, new_line: line_endings.new_line(), pending_cr: false }
+    }
+
+    /// Writes the pending carriage return, then returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if std::mem::take(&mut self.pending_cr) {
+            self.inner.write_all(b"\r")?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for NewLineWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(new_line) = self.new_line else {
+            return self.inner.write(buf)
+        };
+        let mut normalised = Vec::with_capacity(buf.len() + buf.len() / 32);
+        for byte in buf {
+            if std::mem::take(&mut self.pending_cr) && *byte != b'\n' {
+                normalised.push(b'\r');
+            }
+            match byte {
+                b'\r' => self.pending_cr = true,
+                b'\n' => normalised.extend_from_slice(new_line.as_bytes()),
+                _ => normalised.push(*byte)
+            }
+        }
+        self.inner.write_all(&normalised)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalise_line_endings() {
+        let text = "unix\nwindows\r\nlone\rcarriage\n";
+        assert_eq!(LineEndings::Preserve.apply(text), text);
+        assert_eq!(LineEndings::Lf.apply(text), "unix\nwindows\nlone\rcarriage\n");
+        assert_eq!(LineEndings::Crlf.apply(text), "unix\r\nwindows\r\nlone\rcarriage\r\n");
+        assert!(matches!(LineEndings::Lf.apply("unix\n"), Cow::Borrowed(_)));
+        assert!(matches!(LineEndings::Crlf.apply("windows\r\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_writer_across_writes() {
+        let mut writer = NewLineWriter::new(Vec::new(), LineEndings::Lf);
+        for piece in ["windows\r", "\nlone\r", "carriage\r"] {
+            writer.write_all(piece.as_bytes()).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), b"windows\nlone\rcarriage\r");
+    }
+
+    #[test]
+    fn test_split_bom() {
+        assert_eq!(split_bom("\u{feff}{\"a\": 1}"), (BOM, "{\"a\": 1}"));
+        assert_eq!(split_bom("{\"a\": 1}"), ("", "{\"a\": 1}"));
+    }
+}
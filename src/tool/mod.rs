@@ -1,9 +1,40 @@
+pub mod cookiecutter_config;
 pub mod cuttercookie_json;
+pub mod dates;
+pub mod diff;
+pub mod encoding;
+pub mod directives;
+pub mod guids;
+pub mod hooks;
+pub mod infer;
+pub mod licenses;
+pub mod line_endings;
+pub mod package_path;
+pub mod path_safety;
 pub mod process_files;
+pub mod secrets;
+pub mod segment;
+pub mod snapshot;
+pub mod stream;
+pub mod structured;
 pub mod substitution_rule;
+pub mod suggest;
+pub mod variants;
 
 pub use {
-    cuttercookie_json::build_replacer,
-    substitution_rule::{SubstitutionRule, RegexReplacer},
-    process_files::process_files
+    cookiecutter_config::CookiecutterConfig,
+    cuttercookie_json::{build_replacer, load_config, CuttercookieConfig},
+    dates::{DateTemplater, TIME_EXTENSION},
+    substitution_rule::{SubstitutionRule, RegexReplacer, RuleOptions},
+    process_files::{process_files, ProcessOptionsBuilder, ProcessReport},
+    secrets::{SecretPolicy, SecretScanner},
+    structured::StructuredTemplater,
+    encoding::EncodingDetector,
+    infer::{infer_variables, build_inferred_replacer, render_inferred},
+    suggest::{suggest_variables, render_occurrences, render_suggestion},
+    guids::{find_guids, guid_rules, GuidVariable},
+    hooks::{resolve_conditional_paths, write_post_gen_hook},
+    licenses::{find_project_license, LicenseTemplater, LICENSE_VARIABLE},
+    package_path::{package_path_rules, resolve_package_paths},
+    variants::process_variants
 };
\ No newline at end of file
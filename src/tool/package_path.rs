@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::substitution_rule::Boundary;
+use super::{CookiecutterConfig, SubstitutionRule};
+
+/// A dotted package variable, e.g. `com.acme.billing`, laid out as nested directories
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackagePath {
+    pub variable: String,  // Name of the package variable in cookiecutter.json
+    pub segments: Vec<String>,  // Package segments, e.g. `["com", "acme", "billing"]`
+}
+
+impl PackagePath {
+    /// Name of the private variable rendering the package as a slash separated path
+    ///
+    /// A directory name cannot hold a `/`, so the expression lives in cookiecutter.json
+    /// and the collapsed directory is only named after the private variable.
+    pub fn path_variable(&self) -> String {
+        format!("__{}_path", self.variable)
+    }
+
+    /// Value of the private variable in cookiecutter.json
+    pub fn path_expression(&self) -> String {
+        format!("{{{{ cookiecutter.{}.replace('.', '/') }}}}", self.variable)
+    }
+
+    /// Pattern of the package as found in paths and build files, its segments
+    /// separated by `/` or `\`
+    pub fn path_pattern(&self) -> String {
+        let segments: Vec<String> = self.segments.iter().map(|segment| regex::escape(segment)).collect();
+        segments.join(r"[/\\]")
+    }
+
+    /// Tells whether a directory is one of the intermediate directories of the package chain
+    ///
+    /// `com` and `com/acme` are intermediate for `com.acme.billing`, they disappear from
+    /// the template since the last directory of the chain is renamed to the whole path.
+    pub fn is_intermediate_dir(&self, rpath: &Path) -> bool {
+        let components: Vec<_> = rpath.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        (1..self.segments.len()).any(|depth| {
+            components.len() >= depth && components[components.len() - depth..]
+                .iter()
+                .zip(&self.segments[..depth])
+                .all(|(component, segment)| component == segment)
+        })
+    }
+}
+
+/// Resolves the package variables declared in `cuttercookie.json`
+///
+/// # Arguments
+/// * `cookiecutter` - Variables of the template
+/// * `variables` - Names of the package variables
+///
+/// # Returns
+/// * `Result<Vec<PackagePath>, String>` - The packages split into their segments
+///
+/// # Errors
+/// * Returns error if a variable is missing or is not a dotted package name of two segments or more
+pub fn resolve_package_paths(
+    cookiecutter: &CookiecutterConfig,
+    variables: &[String]
+) -> Result<Vec<PackagePath>, String> {
+    let package_regex = Regex::new(r"^[A-Za-z_]\w*(\.[A-Za-z_]\w*)+$").expect("Invalid package regex");
+    variables.iter()
+        .map(|variable| {
+            let value = match cookiecutter.get(variable) {
+                Some(Value::String(value)) => value,
+                Some(_) => return Err(format!("Package variable \"{}\" must be a string", variable)),
+                None => return Err(format!("Package variable \"{}\" is not defined in cookiecutter.json", variable))
+            };
+            if !package_regex.is_match(value) {
+                return Err(format!("Package variable \"{}\" is not a dotted package name: \"{}\"", variable, value))
+            }
+            Ok(PackagePath {
+                variable: variable.clone(),
+                segments: value.split('.').map(str::to_string).collect()
+            })
+        })
+        .collect()
+}
+
+/// Builds the rules replacing the path form of the packages
+///
+/// Templated paths are replaced as a whole, so the rule also collapses the nested
+/// directory chain into a single directory named after the private path variable.
+/// Matches are whole path components, so that a sibling directory such as
+/// `com/acme/billing_old` is left alone.
+pub fn package_path_rules(packages: &[PackagePath]) -> Vec<SubstitutionRule> {
+    packages.iter()
+        .map(|package| {
+            let mut rule = SubstitutionRule::new(package.path_pattern(), package.path_variable());
+            rule.options.boundary = Boundary::Component;
+            rule
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::RegexReplacer;
+
+    fn config(entries: &[(&str, Value)]) -> CookiecutterConfig {
+        let mut config = CookiecutterConfig::new();
+        for (key, value) in entries {
+            config.insert(key, value.clone());
+        }
+        config
+    }
+
+    #[test]
+    fn test_resolve_package_paths() {
+        let cookiecutter = config(&[
+            ("package", Value::String("com.acme.billing".to_string())),
+            ("name", Value::String("billing".to_string())),
+            ("flag", Value::Bool(true)),
+        ]);
+
+        let packages = resolve_package_paths(&cookiecutter, &["package".to_string()]).unwrap();
+        assert_eq!(packages, vec![PackagePath {
+            variable: "package".to_string(),
+            segments: vec!["com".to_string(), "acme".to_string(), "billing".to_string()]
+        }]);
+        assert_eq!(packages[0].path_variable(), "__package_path");
+        assert_eq!(packages[0].path_expression(), "{{ cookiecutter.package.replace('.', '/') }}");
+
+        for variable in ["name", "flag", "missing"] {
+            assert!(resolve_package_paths(&cookiecutter, &[variable.to_string()]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_package_chain_collapse() {
+        let package = PackagePath {
+            variable: "package".to_string(),
+            segments: vec!["com".to_string(), "acme".to_string(), "billing".to_string()]
+        };
+
+        assert!(package.is_intermediate_dir(Path::new("src/main/java/com")));
+        assert!(package.is_intermediate_dir(Path::new("src/main/java/com/acme")));
+        assert!(!package.is_intermediate_dir(Path::new("src/main/java/com/acme/billing")));
+        assert!(!package.is_intermediate_dir(Path::new("src/main/java")));
+
+        let replacer = RegexReplacer::new(package_path_rules(&[package]));
+        assert_eq!(
+            replacer.replace("src/main/java/com/acme/billing/App.java"),
+            "src/main/java/{{cookiecutter.__package_path}}/App.java"
+        );
+        assert_eq!(
+            replacer.replace(r"src\main\java\com\acme\billing"),
+            r"src\main\java\{{cookiecutter.__package_path}}"
+        );
+        assert_eq!(replacer.replace("\"com/acme/billing\""), "\"{{cookiecutter.__package_path}}\"");
+    }
+
+    #[test]
+    fn test_sibling_sharing_the_prefix() {
+        let package = PackagePath {
+            variable: "package".to_string(),
+            segments: vec!["com".to_string(), "acme".to_string(), "billing".to_string()]
+        };
+        let replacer = RegexReplacer::new(package_path_rules(&[package]));
+
+        for path in ["src/com/acme/billing_old/App.java", "src/com/acme/billing-v2", "src/com/acme/billing.txt", "src/xcom/acme/billing"] {
+            assert_eq!(replacer.replace(path), path);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::{Component, Path};
+
+/// Names reserved by Windows, whatever their extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters forbidden in Windows file names
+const INVALID_CHARS: [char; 9] = ['<', '>', ':', '"', '\\', '|', '?', '*', '\0'];
+
+/// Longest file name, in bytes, supported by common file systems
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Checks that a templated path stays inside the output root
+///
+/// # Arguments
+/// * `source` - Relative path of the entry in the source project
+/// * `templated` - Relative path produced by the replacements
+///
+/// # Errors
+/// * Returns error if the templated path is absolute or holds `.` or `..` components
+/// * Returns error if the replacements added path separators, collapsing directories being allowed
+pub fn check_templated_path(source: &Path, templated: &(impl AsRef<Path> + ?Sized)) -> Result<(), String> {
+    let templated_path = templated.as_ref();
+    let templated = templated_path.display();
+    for component in templated_path.components() {
+        match component {
+            Component::Normal(_) => {},
+            Component::ParentDir => return Err(format!("Templated path \"{}\" escapes the output directory", templated)),
+            Component::CurDir => return Err(format!("Templated path \"{}\" holds a \".\" component", templated)),
+            Component::RootDir | Component::Prefix(_) => return Err(format!("Templated path \"{}\" is absolute", templated))
+        }
+    }
+    if templated_path.components().count() > source.components().count() {
+        return Err(format!(
+            "Templated path \"{}\" has more components than \"{}\", a replacement added a path separator",
+            templated,
+            source.display()
+        ))
+    }
+    Ok(())
+}
+
+/// Lists the names of a templated path that are invalid or risky on other platforms
+///
+/// # Returns
+/// * One warning per offending component: reserved Windows names, trailing dots or spaces,
+///   characters forbidden on Windows, control characters and names longer than 255 bytes
+pub fn portability_warnings(templated: &(impl AsRef<Path> + ?Sized)) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let templated = templated.as_ref();
+    for component in templated.components() {
+        let name = component.as_os_str().to_string_lossy();
+        let stem = name.split('.').next().unwrap_or_default().to_uppercase();
+        let problem = if RESERVED_NAMES.contains(&stem.trim_end()) {
+            Some(format!("reserved Windows name \"{}\"", name))
+        } else if name.ends_with('.') || name.ends_with(' ') {
+            Some(format!("name \"{}\" ends with a dot or a space", name))
+        } else if let Some(c) = name.chars().find(|c| INVALID_CHARS.contains(c) || c.is_control()) {
+            Some(format!("name \"{}\" holds {:?}, invalid on Windows", name, c))
+        } else if name.len() > MAX_NAME_LENGTH {
+            Some(format!("name \"{}\" is longer than {} bytes", name, MAX_NAME_LENGTH))
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            warnings.push(format!("{}: {}", templated.display(), problem));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_templated_path() {
+        let source = Path::new("src/com/acme/billing/App.java");
+        assert!(check_templated_path(source, "src/{{cookiecutter.__package_path}}/App.java").is_ok());
+        assert!(check_templated_path(Path::new(""), "").is_ok());
+
+        for templated in ["src/../../etc/passwd", "/etc/passwd", "./App.java", "a/b/c/d/e/f.java"] {
+            assert!(check_templated_path(source, templated).is_err(), "{} should be rejected", templated);
+        }
+    }
+
+    #[test]
+    fn test_portability_warnings() {
+        assert!(portability_warnings("src/{{cookiecutter.name}}/main.rs").is_empty());
+
+        let warnings = portability_warnings("con.txt/notes./a:b");
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].contains("reserved Windows name"));
+        assert!(warnings[1].contains("ends with a dot"));
+        assert!(warnings[2].contains("':'"));
+        assert_eq!(portability_warnings(&"x".repeat(300)).len(), 1);
+    }
+}
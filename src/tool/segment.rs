@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use super::RegexReplacer;
+
+/// A piece of file content, either templatized or emitted verbatim
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),  // Source text, the replacer rules apply to it
+    Literal(String),  // Template syntax or protected text, copied as is
+    Verbatim(String),  // Source text out of reach of the rules, still inspected by the scanners
+}
+
+impl Segment {
+    /// Returns the source text of a segment the scanners inspect, `None` for literals
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            Segment::Text(text) | Segment::Verbatim(text) => Some(text),
+            Segment::Literal(_) => None
+        }
+    }
+
+    /// Builds a segment of the same kind holding another source text
+    pub fn with_source(&self, text: String) -> Segment {
+        match self {
+            Segment::Verbatim(_) => Segment::Verbatim(text),
+            _ => Segment::Text(text)
+        }
+    }
+}
+
+/// Joins segments into the final content, applying the replacer to text segments only
+///
+/// Keeping template syntax in `Literal` segments guarantees that no rule can
+/// rewrite the Jinja tags inserted by cuttercookie.
+///
+/// # Arguments
+/// * `segments` - Segments of a file content
+/// * `replacer` - Rules applied to the text segments
+/// * `warnings` - Receives the matches whose casing cannot be templated
+pub fn render_segments(segments: &[Segment], replacer: &RegexReplacer, warnings: &mut Vec<String>) -> String {
+    segments.iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => replacer.replace_reporting(text, warnings),
+            Segment::Literal(literal) | Segment::Verbatim(literal) => literal.clone()
+        })
+        .collect()
+}
+
+/// Splits the text segments around template snippets, line by line
+///
+/// # Arguments
+/// * `segments` - Segments of a file content
+/// * `find` - Returns the byte ranges of a line to replace, in order, with their literal replacement
+///
+/// # Returns
+/// * The segments where every found range is a `Literal`, literals being left untouched
+///   and verbatim text staying verbatim
+pub fn replace_in_lines<F>(segments: Vec<Segment>, mut find: F) -> Vec<Segment>
+where
+    F: FnMut(&str) -> Vec<(Range<usize>, String)>
+{
+    let mut result = Vec::new();
+    for segment in segments {
+        let Some(text) = segment.source() else {
+            result.push(segment);
+            continue
+        };
+        let mut pending = String::new();
+        for line in text.split_inclusive('\n') {
+            let mut last = 0;
+            for (range, literal) in find(line) {
+                pending.push_str(&line[last..range.start]);
+                if !pending.is_empty() {
+                    result.push(segment.with_source(std::mem::take(&mut pending)));
+                }
+                result.push(Segment::Literal(literal));
+                last = range.end;
+            }
+            pending.push_str(&line[last..]);
+        }
+        if !pending.is_empty() {
+            result.push(segment.with_source(pending));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::SubstitutionRule;
+
+    #[test]
+    fn test_literals_are_not_replaced() {
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("docker".to_string(), "tool".to_string())
+        ]);
+        let segments = vec![
+            Segment::Literal("{% if cookiecutter.use_docker %}".to_string()),
+            Segment::Text("run docker\n".to_string()),
+            Segment::Literal("{% endif %}".to_string())
+        ];
+
+        assert_eq!(
+            render_segments(&segments, &replacer, &mut Vec::new()),
+            "{% if cookiecutter.use_docker %}run {{cookiecutter.tool}}\n{% endif %}"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::process_files::walk_project;
+
+/// Items never scanned when analysing a project
+pub const SCANNER_SKIP_ITEMS: [&str; 1] = [".git"];
+
+/// Relative paths and text contents of a project, read once for analysis
+pub struct ProjectSnapshot {
+    pub paths: Vec<String>,  // Every relative path, directories included, in walk order
+    pub contents: Vec<(String, String)>,  // Relative path and content of every UTF-8 file
+    index: HashMap<String, usize>,  // Position in `contents` of each relative path
+}
+
+impl ProjectSnapshot {
+    /// Returns the content of a text file from its relative path
+    pub fn content(&self, rpath: &str) -> Option<&str> {
+        self.index.get(rpath).map(|&position| self.contents[position].1.as_str())
+    }
+}
+
+/// Reads the relative paths and text contents of a project
+///
+/// # Arguments
+/// * `path` - Root path of the project
+/// * `excluded_items` - List of directory or file names that are not read,
+///   `SCANNER_SKIP_ITEMS` being always excluded
+///
+/// # Returns
+/// * `Result<ProjectSnapshot>` - The snapshot, binary and non UTF-8 files only listed in `paths`
+///
+/// # Errors
+/// * Returns error if `path` is not a directory
+pub fn read_project(path: &str, excluded_items: &[String]) -> Result<ProjectSnapshot> {
+    let root = Path::new(path);
+    if !root.is_dir() {
+        return Err(anyhow::anyhow!("\"{}\" is not a directory", path))
+    }
+
+    let mut excluded = excluded_items.to_vec();
+    excluded.extend(SCANNER_SKIP_ITEMS.iter().map(|s| s.to_string()));
+
+    let mut snapshot = ProjectSnapshot { paths: Vec::new(), contents: Vec::new(), index: HashMap::new() };
+    for entry in walk_project(path, &excluded) {
+        let rpath = match entry.path().strip_prefix(root) {
+            Ok(rpath) if !rpath.as_os_str().is_empty() => rpath.to_string_lossy().into_owned(),
+            _ => continue
+        };
+        if entry.file_type().is_file() {
+            if let Ok(content) = fs::read_to_string(entry.path()) {
+                snapshot.index.insert(rpath.clone(), snapshot.contents.len());
+                snapshot.contents.push((rpath.clone(), content));
+            }
+        }
+        snapshot.paths.push(rpath);
+    }
+    Ok(snapshot)
+}
@@ -0,0 +1,223 @@
+use std::io::{Read, Write};
+
+use super::RegexReplacer;
+
+/// Size in bytes of the chunks read from streamed files
+pub const CHUNK_SIZE: usize = 1 << 20;
+
+/// Size in bytes from which files are streamed rather than read whole
+pub const STREAM_THRESHOLD: u64 = 64 << 20;
+
+/// Bytes of text kept before a window, so that boundaries see the previous characters
+const CONTEXT_BYTES: usize = 8;
+
+/// Bytes of text held back after the matches, so that boundaries see the next characters
+const LOOKAHEAD_BYTES: usize = 8;
+
+/// Returns the largest character boundary of a text not above a position
+fn floor_char_boundary(text: &str, position: usize) -> usize {
+    let mut position = position.min(text.len());
+    while !text.is_char_boundary(position) {
+        position -= 1;
+    }
+    position
+}
+
+/// Reads a text in chunks, cut after a line end whenever the chunk holds one
+///
+/// # Arguments
+/// * `reader` - Source of the text
+/// * `chunk_size` - Number of bytes read at once
+/// * `handle` - Receives the chunks in order
+///
+/// # Returns
+/// * `Result<(), String>` - Success, or the first error of the reader or of `handle`
+///
+/// # Errors
+/// * Returns error if the text is not valid UTF-8
+pub fn read_chunks<R, F>(mut reader: R, chunk_size: usize, mut handle: F) -> Result<(), String>
+where
+    R: Read,
+    F: FnMut(&str) -> Result<(), String>
+{
+    let mut buffer: Vec<u8> = Vec::with_capacity(chunk_size);
+    let mut block = vec![0; chunk_size];
+    loop {
+        let read = reader.read(&mut block).map_err(|err| err.to_string())?;
+        buffer.extend_from_slice(&block[..read]);
+        if read > 0 && buffer.len() < chunk_size {
+            continue
+        }
+
+        // Lines and characters are kept whole, the rest waits for the next chunk
+        let cut = match read {
+            0 => buffer.len(),
+            _ => match buffer.iter().rposition(|byte| *byte == b'\n') {
+                Some(line_end) => line_end + 1,
+                None => match std::str::from_utf8(&buffer) {
+                    Ok(_) => buffer.len(),
+                    Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                    Err(_) => return Err("stream did not contain valid UTF-8".to_string())
+                }
+            }
+        };
+        let chunk = std::str::from_utf8(&buffer[..cut])
+            .map_err(|_| "stream did not contain valid UTF-8".to_string())?;
+        if !chunk.is_empty() {
+            handle(chunk)?;
+        }
+        buffer.drain(..cut);
+        if read == 0 {
+            return Ok(())
+        }
+    }
+}
+
+/// Applies the substitution rules to a text received in pieces, writing it as it goes
+///
+/// Matches may span the pieces: the text after the last possible match start is held
+/// back until enough text follows it, which requires the rules to have bounded matches.
+/// Memory use is bounded by the size of the pieces plus the longest match.
+pub struct StreamReplacer<'a, W: Write> {
+    replacer: &'a RegexReplacer,
+    writer: W,
+    pending: String,  // Text not written yet, after `context` bytes of text already written
+    context: usize,  // Bytes at the start of `pending` only kept as context of the boundaries
+    holdback: usize,  // Bytes held back at the end of `pending` until more text comes
+}
+
+impl<'a, W: Write> StreamReplacer<'a, W> {
+    /// Creates a replacer writing the replaced text to a writer
+    ///
+    /// # Arguments
+    /// * `replacer` - Rules applied to the text
+    /// * `writer` - Receives the replaced text
+    ///
+    /// # Returns
+    /// * The stream replacer, `None` if a rule has matches of unbounded length
+    pub fn new(replacer: &'a RegexReplacer, writer: W) -> Option<Self> {
+        Some(Self {
+            replacer,
+            writer,
+            pending: String::new(),
+            context: 0,
+            holdback: replacer.max_match_len()? + LOOKAHEAD_BYTES
+        })
+    }
+
+    /// Appends text the rules apply to
+    ///
+    /// # Arguments
+    /// * `text` - Next piece of text
+    /// * `warnings` - Receives the case insensitive matches left unchanged
+    pub fn push_text(&mut self, text: &str, warnings: &mut Vec<String>) -> Result<(), String> {
+        self.pending.push_str(text);
+        if self.pending.len() - self.context <= self.holdback {
+            return Ok(())
+        }
+        let limit = floor_char_boundary(&self.pending, self.pending.len() - self.holdback);
+        let (replaced, stop) = self.replacer.replace_window(&self.pending, self.context, limit, warnings);
+        self.writer.write_all(replaced.as_bytes()).map_err(|err| err.to_string())?;
+
+        let keep = floor_char_boundary(&self.pending, stop.saturating_sub(CONTEXT_BYTES));
+        self.pending.drain(..keep);
+        self.context = stop - keep;
+        Ok(())
+    }
+
+    /// Appends text copied as is, which ends the text the rules apply to
+    ///
+    /// # Arguments
+    /// * `literal` - Text written unchanged
+    /// * `warnings` - Receives the case insensitive matches left unchanged
+    pub fn push_literal(&mut self, literal: &str, warnings: &mut Vec<String>) -> Result<(), String> {
+        self.flush_text(warnings)?;
+        self.writer.write_all(literal.as_bytes()).map_err(|err| err.to_string())
+    }
+
+    /// Writes the held back text, then flushes the writer
+    ///
+    /// # Arguments
+    /// * `warnings` - Receives the case insensitive matches left unchanged
+    ///
+    /// # Returns
+    /// * `Result<W, String>` - The writer, or the error of the last writes
+    pub fn finish(mut self, warnings: &mut Vec<String>) -> Result<W, String> {
+        self.flush_text(warnings)?;
+        self.writer.flush().map_err(|err| err.to_string())?;
+        Ok(self.writer)
+    }
+
+    /// Replaces and writes the whole pending text
+    fn flush_text(&mut self, warnings: &mut Vec<String>) -> Result<(), String> {
+        let (replaced, _) = self.replacer.replace_window(&self.pending, self.context, self.pending.len(), warnings);
+        self.writer.write_all(replaced.as_bytes()).map_err(|err| err.to_string())?;
+        self.pending.clear();
+        self.context = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{RuleOptions, SubstitutionRule};
+    use super::super::substitution_rule::Boundary;
+
+    /// Replaces a text streamed in pieces of a given size
+    fn stream(replacer: &RegexReplacer, content: &str, piece: usize) -> String {
+        let mut replacer = StreamReplacer::new(replacer, Vec::new()).expect("Bounded rules");
+        let mut warnings = Vec::new();
+        let mut start = 0;
+        while start < content.len() {
+            let end = (start + piece..=content.len()).find(|end| content.is_char_boundary(*end)).unwrap_or(content.len());
+            replacer.push_text(&content[start..end], &mut warnings).unwrap();
+            start = end;
+        }
+        String::from_utf8(replacer.finish(&mut warnings).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_matches_spanning_pieces() {
+        let word = RuleOptions { boundary: Boundary::Word, ..RuleOptions::default() };
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("acme-widget".to_string(), "project_slug".to_string()),
+            SubstitutionRule::new("Acme[ _]Corp".to_string(), "company".to_string()),
+            SubstitutionRule::new("acme".to_string(), "org".to_string())
+        ]).with_options(
+            [("org".to_string(), word)].into_iter().collect(),
+            RuleOptions::default()
+        ).unwrap();
+        let content = "acme-widget by Acme Corp, acme and acmes; é acme_widget Acme_Corp\nacme";
+
+        let expected = replacer.replace(content);
+        for piece in 1..content.len() {
+            assert_eq!(stream(&replacer, content, piece), expected, "pieces of {} bytes", piece);
+        }
+    }
+
+    #[test]
+    fn test_unbounded_rules_are_not_streamed() {
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("acme[a-z]+".to_string(), "org".to_string())
+        ]);
+        assert!(StreamReplacer::new(&replacer, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let content = "first line\nsecond é line\nno line end é";
+        for chunk_size in 1..content.len() + 2 {
+            let mut chunks = Vec::new();
+            read_chunks(content.as_bytes(), chunk_size, |chunk| {
+                chunks.push(chunk.to_string());
+                Ok(())
+            }).unwrap();
+            assert_eq!(chunks.concat(), content);
+            assert!(chunks.iter().rev().skip(1).all(|chunk| chunk.ends_with('\n') || !chunk.contains('\n')));
+        }
+
+        let invalid: &[u8] = b"valid\n\xff\xfe\n";
+        assert!(read_chunks(invalid, 4, |_| Ok(())).is_err());
+    }
+}
@@ -0,0 +1,513 @@
+use std::ops::Range;
+use std::path::Path;
+
+use globset::{Glob, GlobMatcher};
+use yaml_rust2::parser::{MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::Event;
+
+use super::cuttercookie_json::StructuredConfig;
+use super::segment::Segment;
+
+/// A step of a document path, from the root to a value
+#[derive(Debug, Clone, PartialEq)]
+enum PathStep {
+    Key(String),  // Member of an object or table
+    Index(usize),  // Item of an array
+}
+
+/// Document path of a string value and byte range of its raw text, quotes excluded
+type StringValue = (Vec<PathStep>, Range<usize>);
+
+/// A step of a document path pattern
+#[derive(Debug, Clone, PartialEq)]
+enum PatternStep {
+    Key(String),  // Given member
+    AnyKey,  // Every member, written `*`
+    Index(usize),  // Given item, written `[n]`
+    AnyIndex,  // Every item, written `[*]`
+}
+
+/// Parses a document path pattern such as `tool.poetry.authors[*]`
+fn parse_pattern(pattern: &str) -> Result<Vec<PatternStep>, String> {
+    let invalid = || format!("Invalid document path \"{}\"", pattern);
+    let mut steps = Vec::new();
+    for part in pattern.split('.') {
+        let (key, indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        match key {
+            "" if indices.is_empty() => return Err(invalid()),
+            "" => {},
+            "*" => steps.push(PatternStep::AnyKey),
+            key => steps.push(PatternStep::Key(key.to_string()))
+        }
+        for index in indices.split_inclusive(']') {
+            let index = index.strip_prefix('[').and_then(|i| i.strip_suffix(']')).ok_or_else(invalid)?;
+            steps.push(match index {
+                "*" => PatternStep::AnyIndex,
+                index => PatternStep::Index(index.parse().map_err(|_| invalid())?)
+            });
+        }
+    }
+    Ok(steps)
+}
+
+/// Tells whether a pattern designates a document path
+fn pattern_matches(pattern: &[PatternStep], path: &[PathStep]) -> bool {
+    pattern.len() == path.len() && pattern.iter().zip(path).all(|(step, path_step)| match (step, path_step) {
+        (PatternStep::Key(key), PathStep::Key(path_key)) => key == path_key,
+        (PatternStep::AnyKey, PathStep::Key(_)) | (PatternStep::AnyIndex, PathStep::Index(_)) => true,
+        (PatternStep::Index(index), PathStep::Index(path_index)) => index == path_index,
+        _ => false
+    })
+}
+
+/// Files whose replacements are restricted to some values of their document
+#[derive(Debug, Clone)]
+struct StructuredRule {
+    files: GlobMatcher,  // Relative paths of the files
+    paths: Vec<Vec<PatternStep>>,  // Document paths of the replaced values
+}
+
+/// Restricts the replacements of JSON, TOML and YAML files to the values at given document paths
+#[derive(Debug, Clone)]
+pub struct StructuredTemplater {
+    rules: Vec<StructuredRule>,
+}
+
+impl StructuredTemplater {
+    /// Creates a templater from the `structured` entries of `cuttercookie.json`
+    ///
+    /// # Errors
+    /// * Returns error if a glob pattern or a document path is invalid
+    pub fn new(configs: &[StructuredConfig]) -> Result<Self, String> {
+        let rules = configs.iter()
+            .map(|config| Ok(StructuredRule {
+                files: Glob::new(&config.files).map_err(|err| err.to_string())?.compile_matcher(),
+                paths: config.paths.iter().map(|path| parse_pattern(path)).collect::<Result<_, String>>()?
+            }))
+            .collect::<Result<_, String>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Tells whether a file is handled in structured mode
+    pub fn matches(&self, rpath: &Path) -> bool {
+        self.rules.iter().any(|rule| rule.files.is_match(rpath))
+    }
+
+    /// Splits a structured file into the string values to templatize and verbatim text
+    ///
+    /// # Arguments
+    /// * `rpath` - Relative path of the file, its extension selecting the format
+    /// * `content` - Content of the file
+    ///
+    /// # Returns
+    /// * `Result<Vec<Segment>, String>` - `Text` segments for the string values at the
+    ///   document paths, `Verbatim` segments for everything else
+    ///
+    /// # Errors
+    /// * Returns error if the format is unsupported or the document cannot be parsed
+    pub fn apply(&self, rpath: &Path, content: &str) -> Result<Vec<Segment>, String> {
+        let extension = rpath.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let values = match extension.as_str() {
+            "json" => json_string_values(content)?,
+            "toml" => toml_string_values(content)?,
+            "yaml" | "yml" => yaml_string_values(content)?,
+            _ => return Err(format!("Structured mode does not support \"{}\"", rpath.display()))
+        };
+        let patterns: Vec<&Vec<PatternStep>> = self.rules.iter()
+            .filter(|rule| rule.files.is_match(rpath))
+            .flat_map(|rule| &rule.paths)
+            .collect();
+
+        let mut segments = Vec::new();
+        let mut last = 0;
+        for (path, range) in values {
+            if range.is_empty() || !patterns.iter().any(|pattern| pattern_matches(pattern, &path)) {
+                continue
+            }
+            if range.start > last {
+                segments.push(Segment::Verbatim(content[last..range.start].to_string()));
+            }
+            segments.push(Segment::Text(content[range.clone()].to_string()));
+            last = range.end;
+        }
+        if last < content.len() {
+            segments.push(Segment::Verbatim(content[last..].to_string()));
+        }
+        Ok(segments)
+    }
+}
+
+/// Lists the raw text ranges, quotes excluded, of the string values of a JSON document
+fn json_string_values(content: &str) -> Result<Vec<StringValue>, String> {
+    let mut reader = JsonReader { bytes: content.as_bytes(), position: 0, values: Vec::new() };
+    reader.value(&mut Vec::new())?;
+    reader.skip_whitespace();
+    if reader.position < reader.bytes.len() {
+        return Err(reader.error("trailing characters"))
+    }
+    Ok(reader.values)
+}
+
+/// Minimal JSON reader recording where string values are
+struct JsonReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    values: Vec<StringValue>,
+}
+
+impl JsonReader<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid JSON at byte {}: {}", self.position, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.position).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.position) {
+            Some(b) if *b == byte => {
+                self.position += 1;
+                Ok(())
+            },
+            _ => Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    /// Reads a string and returns the range between its quotes
+    fn string(&mut self) -> Result<Range<usize>, String> {
+        self.expect(b'"')?;
+        let start = self.position;
+        loop {
+            match self.bytes.get(self.position) {
+                Some(b'"') => break,
+                Some(b'\\') => self.position += 2,
+                Some(_) => self.position += 1,
+                None => return Err(self.error("unterminated string"))
+            }
+        }
+        self.position += 1;
+        Ok(start..self.position - 1)
+    }
+
+    fn value(&mut self, path: &mut Vec<PathStep>) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.position) {
+            Some(b'{') => {
+                self.position += 1;
+                self.skip_whitespace();
+                if self.bytes.get(self.position) == Some(&b'}') {
+                    self.position += 1;
+                    return Ok(())
+                }
+                loop {
+                    let key_range = self.string()?;
+                    let key = serde_json::from_slice::<String>(&self.bytes[key_range.start - 1..key_range.end + 1])
+                        .map_err(|err| self.error(&err.to_string()))?;
+                    self.expect(b':')?;
+                    path.push(PathStep::Key(key));
+                    self.value(path)?;
+                    path.pop();
+                    self.skip_whitespace();
+                    match self.bytes.get(self.position) {
+                        Some(b',') => self.position += 1,
+                        Some(b'}') => break,
+                        _ => return Err(self.error("expected ',' or '}'"))
+                    }
+                }
+                self.position += 1;
+            },
+            Some(b'[') => {
+                self.position += 1;
+                self.skip_whitespace();
+                if self.bytes.get(self.position) == Some(&b']') {
+                    self.position += 1;
+                    return Ok(())
+                }
+                let mut index = 0;
+                loop {
+                    path.push(PathStep::Index(index));
+                    self.value(path)?;
+                    path.pop();
+                    index += 1;
+                    self.skip_whitespace();
+                    match self.bytes.get(self.position) {
+                        Some(b',') => self.position += 1,
+                        Some(b']') => break,
+                        _ => return Err(self.error("expected ',' or ']'"))
+                    }
+                }
+                self.position += 1;
+            },
+            Some(b'"') => {
+                let range = self.string()?;
+                self.values.push((path.clone(), range));
+            },
+            Some(_) => {
+                let start = self.position;
+                while self.bytes.get(self.position).is_some_and(|b| !b",]} \t\r\n".contains(b)) {
+                    self.position += 1;
+                }
+                if self.position == start {
+                    return Err(self.error("unexpected character"))
+                }
+            },
+            None => return Err(self.error("unexpected end"))
+        }
+        Ok(())
+    }
+}
+
+/// Lists the raw text ranges, quotes excluded, of the string values of a TOML document
+fn toml_string_values(content: &str) -> Result<Vec<StringValue>, String> {
+    let document = toml_edit::ImDocument::parse(content).map_err(|err| err.to_string())?;
+    let mut values = Vec::new();
+    toml_table(content, document.as_table(), &mut Vec::new(), &mut values);
+    values.sort_by_key(|(_, range)| range.start);
+    Ok(values)
+}
+
+fn toml_table(
+    content: &str,
+    table: &toml_edit::Table,
+    path: &mut Vec<PathStep>,
+    values: &mut Vec<StringValue>
+) {
+    for (key, item) in table.iter() {
+        path.push(PathStep::Key(key.to_string()));
+        match item {
+            toml_edit::Item::Value(value) => toml_value(content, value, path, values),
+            toml_edit::Item::Table(table) => toml_table(content, table, path, values),
+            toml_edit::Item::ArrayOfTables(tables) => {
+                for (index, table) in tables.iter().enumerate() {
+                    path.push(PathStep::Index(index));
+                    toml_table(content, table, path, values);
+                    path.pop();
+                }
+            },
+            toml_edit::Item::None => {}
+        }
+        path.pop();
+    }
+}
+
+fn toml_value(
+    content: &str,
+    value: &toml_edit::Value,
+    path: &mut Vec<PathStep>,
+    values: &mut Vec<StringValue>
+) {
+    match value {
+        toml_edit::Value::String(_) => {
+            if let Some(span) = value.span() {
+                let quotes = match &content[span.clone()] {
+                    raw if raw.starts_with("\"\"\"") || raw.starts_with("'''") => 3,
+                    _ => 1
+                };
+                values.push((path.clone(), span.start + quotes..span.end - quotes));
+            }
+        },
+        toml_edit::Value::Array(array) => {
+            for (index, item) in array.iter().enumerate() {
+                path.push(PathStep::Index(index));
+                toml_value(content, item, path, values);
+                path.pop();
+            }
+        },
+        toml_edit::Value::InlineTable(table) => {
+            for (key, item) in table.iter() {
+                path.push(PathStep::Key(key.to_string()));
+                toml_value(content, item, path, values);
+                path.pop();
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Lists the raw text ranges, quotes excluded, of the string scalars of a YAML document
+///
+/// Only scalars written as their value are listed: plain and quoted scalars without
+/// escapes or line folding, block scalars being left verbatim.
+fn yaml_string_values(content: &str) -> Result<Vec<StringValue>, String> {
+    let mut receiver = YamlReceiver {
+        content,
+        byte_offsets: content.char_indices().map(|(offset, _)| offset).collect(),
+        frames: Vec::new(),
+        path: Vec::new(),
+        values: Vec::new()
+    };
+    Parser::new_from_str(content)
+        .load(&mut receiver, true)
+        .map_err(|err| err.to_string())?;
+    Ok(receiver.values)
+}
+
+/// Position inside a YAML collection
+enum YamlFrame {
+    Mapping(Option<String>),  // Key of the pending value, `None` while a key is expected
+    Sequence(usize),  // Index of the next item
+}
+
+/// Tracks the document path of the YAML parser events
+struct YamlReceiver<'a> {
+    content: &'a str,
+    byte_offsets: Vec<usize>,  // Byte offset of every character, markers counting characters
+    frames: Vec<YamlFrame>,
+    path: Vec<PathStep>,
+    values: Vec<StringValue>,
+}
+
+impl YamlReceiver<'_> {
+    /// Returns the step of the value starting now, or `None` when the event is a mapping key
+    fn next_step(&mut self) -> Option<Option<PathStep>> {
+        match self.frames.last_mut() {
+            Some(YamlFrame::Mapping(key)) => key.take().map(|key| Some(PathStep::Key(key))),
+            Some(YamlFrame::Sequence(index)) => {
+                *index += 1;
+                Some(Some(PathStep::Index(*index - 1)))
+            },
+            None => Some(None)
+        }
+    }
+
+    /// Returns the byte range of a scalar written exactly as its value
+    fn scalar_range(&self, value: &str, style: TScalarStyle, mark: Marker) -> Option<Range<usize>> {
+        let start = *self.byte_offsets.get(mark.index())?;
+        let start = match style {
+            TScalarStyle::Plain => start,
+            TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => start + 1,
+            _ => return None
+        };
+        let end = start + value.len();
+        let quote_closed = match style {
+            TScalarStyle::SingleQuoted => self.content[end..].starts_with('\''),
+            TScalarStyle::DoubleQuoted => self.content[end..].starts_with('"'),
+            _ => true
+        };
+        (self.content.get(start..end) == Some(value) && quote_closed).then_some(start..end)
+    }
+}
+
+impl MarkedEventReceiver for YamlReceiver<'_> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, style, _, _) => match self.next_step() {
+                None => {
+                    if let Some(YamlFrame::Mapping(key)) = self.frames.last_mut() {
+                        *key = Some(value);
+                    }
+                },
+                Some(step) => {
+                    if let Some(range) = self.scalar_range(&value, style, mark) {
+                        let mut path = self.path.clone();
+                        path.extend(step);
+                        self.values.push((path, range));
+                    }
+                }
+            },
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                if let Some(Some(step)) = self.next_step() {
+                    self.path.push(step);
+                }
+                self.frames.push(match event {
+                    Event::MappingStart(..) => YamlFrame::Mapping(None),
+                    _ => YamlFrame::Sequence(0)
+                });
+            },
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.frames.pop();
+                if !self.frames.is_empty() {
+                    self.path.pop();
+                }
+            },
+            Event::Alias(..) => {
+                self.next_step();
+            },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templater(files: &str, paths: &[&str]) -> StructuredTemplater {
+        StructuredTemplater::new(&[StructuredConfig {
+            files: files.to_string(),
+            paths: paths.iter().map(|path| path.to_string()).collect()
+        }]).unwrap()
+    }
+
+    /// Returns the text segments, the verbatim ones being checked to rebuild the content
+    fn texts(segments: &[Segment], content: &str) -> Vec<String> {
+        let rebuilt: String = segments.iter().map(|s| s.source().unwrap_or_default()).collect();
+        assert_eq!(rebuilt, content);
+        segments.iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text.clone()),
+                _ => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(parse_pattern("tool.poetry.authors[*]").unwrap(), vec![
+            PatternStep::Key("tool".to_string()),
+            PatternStep::Key("poetry".to_string()),
+            PatternStep::Key("authors".to_string()),
+            PatternStep::AnyIndex,
+        ]);
+        assert_eq!(parse_pattern("bin[0].*").unwrap(), vec![
+            PatternStep::Key("bin".to_string()),
+            PatternStep::Index(0),
+            PatternStep::AnyKey,
+        ]);
+        assert!(parse_pattern("a..b").is_err());
+        assert!(parse_pattern("a[x]").is_err());
+    }
+
+    #[test]
+    fn test_json_values() {
+        let content = "{\n  \"name\": \"acme\",\n  \"dependencies\": {\"acme\": \"^1.0\"},\n  \"keywords\": [\"acme\", \"tool\"]\n}\n";
+        let segments = templater("package.json", &["name", "keywords[*]"])
+            .apply(Path::new("package.json"), content)
+            .unwrap();
+
+        assert_eq!(texts(&segments, content), vec!["acme", "acme", "tool"]);
+    }
+
+    #[test]
+    fn test_toml_values() {
+        let content = "# acme manifest\n[package]\nname = \"acme\" # acme\nauthors = ['Jane <jane@acme.io>']\n\n[dependencies]\nacme = \"1.0\"\n";
+        let segments = templater("Cargo.toml", &["package.name", "package.authors[*]"])
+            .apply(Path::new("Cargo.toml"), content)
+            .unwrap();
+
+        assert_eq!(texts(&segments, content), vec!["acme", "Jane <jane@acme.io>"]);
+    }
+
+    #[test]
+    fn test_yaml_values() {
+        let content = "# acme\nname: acme\nauthors:\n  - \"Jane\"\n  - 'John'\nacme: acme\ndescription: |\n  acme\n";
+        let segments = templater("*.yaml", &["name", "authors[*]", "description"])
+            .apply(Path::new("pubspec.yaml"), content)
+            .unwrap();
+
+        assert_eq!(texts(&segments, content), vec!["acme", "Jane", "John"]);
+    }
+
+    #[test]
+    fn test_invalid_documents() {
+        let templater = templater("*", &["name"]);
+        assert!(templater.apply(Path::new("a.json"), "{\"name\": }").is_err());
+        assert!(templater.apply(Path::new("a.toml"), "name = ").is_err());
+        assert!(templater.apply(Path::new("a.ini"), "name = x").is_err());
+    }
+}
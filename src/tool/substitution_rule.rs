@@ -1,14 +1,199 @@
+use std::borrow::Cow;
 use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
 
+use aho_corasick::{AhoCorasick, Input, MatchKind};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use regex::Regex;
+use regex_automata::meta::Regex as MetaRegex;
+use regex_automata::Anchored;
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+/// Parts of a project a rule applies to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleTarget {
+    #[default]
+    Both,  // Paths and file contents
+    Paths,  // File and directory names only
+    Contents,  // File contents only
+}
+
+/// Restricts where a rule applies
+#[derive(Debug, Clone)]
+pub struct RuleScope {
+    include: GlobSet,  // Files whose contents are replaced, every file when empty
+    exclude: GlobSet,  // Files whose contents are never replaced
+    target: RuleTarget,  // Paths, contents or both
+}
+
+impl Default for RuleScope {
+    fn default() -> Self {
+        Self { include: GlobSet::empty(), exclude: GlobSet::empty(), target: RuleTarget::Both }
+    }
+}
+
+impl RuleScope {
+    /// Creates a scope from glob patterns matched against the relative path of the files
+    ///
+    /// # Errors
+    /// * Returns error if a glob pattern is invalid
+    pub fn new(include: &[String], exclude: &[String], target: RuleTarget) -> Result<Self, String> {
+        let build = |patterns: &[String]| {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(Glob::new(pattern).map_err(|err| err.to_string())?);
+            }
+            builder.build().map_err(|err| err.to_string())
+        };
+        Ok(Self { include: build(include)?, exclude: build(exclude)?, target })
+    }
+
+    /// Tells whether the rule applies to the paths of the project
+    fn applies_to_paths(&self) -> bool {
+        self.target != RuleTarget::Contents
+    }
+
+    /// Tells whether the rule applies to the content of a file
+    fn applies_to_content(&self, rpath: &Path) -> bool {
+        self.target != RuleTarget::Paths
+            && (self.include.is_empty() || self.include.is_match(rpath))
+            && !self.exclude.is_match(rpath)
+    }
+}
+
+/// Positions where the matches of a rule may start and end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Boundary {
+    #[default]
+    None,  // Anywhere
+    Word,  // Not inside a word, `_` being part of words
+    Identifier,  // At identifier segment boundaries: separators, case humps and ends of text
+    #[serde(skip)]
+    Component,  // At path component boundaries: path separators, quotes, spaces and ends of text
+}
+
+impl Boundary {
+    /// Tells whether a match of a text starts and ends on the boundary
+    fn accepts(&self, content: &str, start: usize, end: usize) -> bool {
+        let before = content[..start].chars().next_back();
+        let mut matched = content[start..end].chars();
+        let (first, second) = (matched.next(), matched.next());
+        let last = content[start..end].chars().next_back();
+        let mut after = content[end..].chars();
+        let (next, next_next) = (after.next(), after.next());
+        match self {
+            Boundary::None => true,
+            Boundary::Word => word_edge(before, first) && word_edge(next, last),
+            Boundary::Identifier => identifier_edge(before, first, second) && identifier_edge_end(last, next, next_next),
+            Boundary::Component => component_edge(before) && component_edge(next)
+        }
+    }
+}
+
+/// Tells whether a character next to a match ends the path component, `com/acme`
+/// ending before `/` but neither before `_old` nor before `.txt`
+fn component_edge(outside: Option<char>) -> bool {
+    !outside.is_some_and(|c| c.is_alphanumeric() || "_-.".contains(c))
+}
+
+/// Tells whether a word character is not glued to another word character
+fn word_edge(outside: Option<char>, inside: Option<char>) -> bool {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    !is_word(inside) || !is_word(outside)
+}
+
+/// Tells whether an identifier segment starts between two characters
+///
+/// Segments start after a separator, at a lower to upper case hump (`myApp`),
+/// or at the last capital of an acronym followed by lower case (`HTTPServer`).
+fn identifier_edge(before: Option<char>, first: Option<char>, second: Option<char>) -> bool {
+    match (before, first) {
+        (Some(before), Some(first)) if before.is_alphanumeric() && first.is_alphanumeric() => {
+            (first.is_uppercase() && !before.is_uppercase())
+                || (first.is_uppercase() && before.is_uppercase() && second.is_some_and(char::is_lowercase))
+        },
+        _ => true
+    }
+}
+
+/// Tells whether an identifier segment ends between two characters
+fn identifier_edge_end(last: Option<char>, next: Option<char>, next_next: Option<char>) -> bool {
+    match (last, next) {
+        (Some(last), Some(next)) if last.is_alphanumeric() && next.is_alphanumeric() => {
+            (next.is_uppercase() && !last.is_uppercase())
+                || (next.is_uppercase() && last.is_uppercase() && next_next.is_some_and(char::is_lowercase))
+        },
+        _ => true
+    }
+}
+
+/// Transformation of a value applied by a Jinja filter
+type CaseFilter = fn(&str) -> String;
+
+/// Jinja filters reproducing the casing of a match from the value of its variable
+const CASE_FILTERS: [(&str, CaseFilter); 4] = [
+    ("lower", |value| value.to_lowercase()),
+    ("upper", |value| value.to_uppercase()),
+    ("title", jinja_title),
+    ("capitalize", jinja_capitalize),
+];
+
+/// Upper cases the first letter of every word and lower cases the others, as the `title` filter
+fn jinja_title(value: &str) -> String {
+    let mut word_start = true;
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_whitespace() || "-({[<".contains(c) {
+            result.push(c);
+            word_start = true;
+        } else if word_start {
+            result.extend(c.to_uppercase());
+            word_start = false;
+        } else {
+            result.extend(c.to_lowercase());
+        }
+    }
+    result
+}
+
+/// Upper cases the first character and lower cases the others, as the `capitalize` filter
+fn jinja_capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new()
+    }
+}
+
+/// Options refining where the rule of a variable applies and matches
+#[derive(Debug, Clone, Default)]
+pub struct RuleOptions {
+    pub scope: RuleScope,  // Paths and files the rule applies to
+    pub boundary: Boundary,  // Positions where matches may start and end
+    pub ignore_case: bool,  // Matches any casing, templated with the filter reproducing it
+    pub priority: i32,  // Wins over lower priorities among matches starting at the same position
+    pub normalize: bool,  // Also matches the NFC and NFD forms of the pattern
+}
 
 /// Represents a single regex substitution rule with pattern and replacement
 ///
 /// Each rule defines a pattern to match and its corresponding replacement value,
 /// where the replacement is automatically wrapped in cookiecutter template syntax.
+#[derive(Debug, Clone)]
 pub struct SubstitutionRule {
     pub pattern: String,  // The regex pattern to match
     pub replacement: String,  // The value to replace matches with
+    pub variable: Option<String>,  // Variable of the replacement, `None` for raw snippets
+    pub options: RuleOptions,  // Scope, boundary, casing and priority of the matches
+    literal: bool,  // The pattern holds no regex syntax, set when the replacer is built
+    anchored_regex: Option<MetaRegex>,  // Pattern searched anchored at a position, compiled for the regex rules
+    variant: bool,  // Matches a normalisation form of another rule, added when the replacer is built
 }
 
 impl SubstitutionRule {
@@ -16,34 +201,319 @@ impl SubstitutionRule {
     pub fn new(pattern: String, replacement: String) -> Self {
         Self {
             pattern,
-            replacement: String::from("{{cookiecutter.") + replacement.as_str() + "}}"
+            replacement: String::from("{{cookiecutter.") + replacement.as_str() + "}}",
+            variable: Some(replacement),
+            options: RuleOptions::default(),
+            literal: false,
+            anchored_regex: None,
+            variant: false
+        }
+    }
+
+    /// Creates a substitution rule whose replacement is a Jinja snippet used as is
+    pub fn raw(pattern: String, replacement: String) -> Self {
+        Self {
+            pattern,
+            replacement,
+            variable: None,
+            options: RuleOptions::default(),
+            literal: false,
+            anchored_regex: None,
+            variant: false
+        }
+    }
+
+    /// Names the rule in diagnostics, by its variable or its pattern
+    fn name(&self) -> String {
+        match &self.variable {
+            Some(variable) => variable.clone(),
+            None => format!("\"{}\"", self.pattern)
+        }
+    }
+
+    /// Returns the pattern matched in the content, case insensitive if the options ask so
+    fn matching_pattern(&self) -> String {
+        match self.options.ignore_case {
+            true => format!("(?i:{})", self.pattern),
+            false => self.pattern.clone()
+        }
+    }
+
+    /// Returns the replacement of a match, or `None` if its casing cannot be expressed
+    ///
+    /// Case insensitive matches are templated with the Jinja filter turning the value
+    /// of the variable into the matched text.
+    fn replacement_for(&self, matched: &str) -> Option<String> {
+        if !self.options.ignore_case || matched == self.pattern {
+            return Some(self.replacement.clone())
+        }
+        let variable = self.variable.as_ref()?;
+        CASE_FILTERS.iter()
+            .find(|(_, filter)| filter(&self.pattern) == matched)
+            .map(|(name, _)| format!("{{{{cookiecutter.{}|{}}}}}", variable, name))
+    }
+
+    /// Returns the rule followed by the rules matching the NFC and NFD forms of its pattern
+    ///
+    /// Forms equal to the pattern are left out, as are all forms of rules without `normalize`.
+    fn with_normalization_variants(self) -> Vec<SubstitutionRule> {
+        let forms: [String; 2] = [self.pattern.nfc().collect(), self.pattern.nfd().collect()];
+        let mut rules = vec![self];
+        if !rules[0].options.normalize {
+            return rules
         }
+        for form in forms {
+            if !rules.iter().any(|rule| rule.pattern == form) {
+                rules.push(SubstitutionRule { pattern: form, variant: true, ..rules[0].clone() });
+            }
+        }
+        rules
+    }
+
+    /// Returns the maximum length in bytes of a match, `None` if unbounded
+    fn max_match_len(&self) -> Option<usize> {
+        if self.literal {
+            return Some(self.pattern.len())
+        }
+        let hir = regex_syntax::parse(&self.matching_pattern()).ok()?;
+        hir.properties().maximum_len()
+    }
+
+    /// Returns the end of a match of the rule starting at `start`, on its boundary
+    fn match_at(&self, content: &str, start: usize) -> Option<usize> {
+        // The whole content is searched, so that boundaries and look-arounds see the text before `start`
+        let end = match &self.anchored_regex {
+            Some(regex) => regex.find(regex_automata::Input::new(content).range(start..).anchored(Anchored::Yes))?.end(),
+            None if content[start..].starts_with(&self.pattern) => start + self.pattern.len(),
+            None => return None
+        };
+        self.options.boundary.accepts(content, start, end).then_some(end)
+    }
+}
+
+/// Finds the leftmost match of a set of rules
+///
+/// Literal rules are searched with an Aho-Corasick automaton, leftmost-longest,
+/// the other rules with a single alternation regex, leftmost-first.
+#[derive(Debug, Clone)]
+struct MatchEngine {
+    literals: Option<(AhoCorasick, Vec<usize>)>,  // Automaton of the literal rules and their indices
+    regex: Option<(Regex, Vec<usize>)>,  // Alternation of the other rules, one group per rule, and their indices
+    overlaps: Arc<OnceLock<OverlapFinder>>,  // Finder of every match, built for the overlap diagnostic
+}
+
+/// A match of a rule
+type RuleMatch = (usize, Range<usize>);
+
+impl MatchEngine {
+    /// Builds the engine of rules sorted by precedence
+    fn new(rules: &[SubstitutionRule]) -> Self {
+        let (literals, patterns): (Vec<usize>, Vec<usize>) = (0..rules.len())
+            .filter(|index| !rules[*index].pattern.is_empty())
+            .partition(|index| rules[*index].literal);
+
+        let literals = (!literals.is_empty()).then(|| {
+            let automaton = AhoCorasick::builder()
+                .match_kind(MatchKind::LeftmostLongest)
+                .build(literals.iter().map(|index| &rules[*index].pattern))
+                .expect("Invalid literal automaton");
+            (automaton, literals)
+        });
+        let regex = (!patterns.is_empty()).then(|| {
+            let combined_pattern = patterns.iter()
+                .map(|index| format!("({})", rules[*index].matching_pattern()))
+                .collect::<Vec<_>>()
+                .join("|");
+            (Regex::new(&combined_pattern).expect("Invalid combined regex"), patterns)
+        });
+        Self { literals, regex, overlaps: Arc::default() }
+    }
+
+    /// Returns the leftmost literal match starting at or after `position`
+    fn find_literal(&self, content: &str, position: usize) -> Option<RuleMatch> {
+        let (automaton, indices) = self.literals.as_ref()?;
+        let found = automaton.find(Input::new(content).span(position..content.len()))?;
+        Some((indices[found.pattern().as_usize()], found.range()))
+    }
+
+    /// Returns the start of the leftmost regex match starting at or after `position`
+    fn find_regex(&self, content: &str, position: usize) -> Option<usize> {
+        let (regex, _) = self.regex.as_ref()?;
+        Some(regex.find_at(content, position)?.start())
+    }
+
+    /// Tells whether any rule matches somewhere, boundaries aside
+    fn is_match(&self, content: &str) -> bool {
+        self.literals.as_ref().is_some_and(|(automaton, _)| automaton.is_match(content))
+            || self.regex.as_ref().is_some_and(|(regex, _)| regex.is_match(content))
+    }
+
+    /// Indices of the regex rules
+    fn regex_rules(&self) -> &[usize] {
+        self.regex.as_ref().map(|(_, indices)| indices.as_slice()).unwrap_or_default()
+    }
+}
+
+/// Finds every match of every rule, overlapping ones included
+#[derive(Debug, Clone)]
+struct OverlapFinder {
+    literals: Option<(AhoCorasick, Vec<usize>)>,  // Automaton reporting overlapping literal matches
+    regexes: Vec<(usize, Regex)>,  // Regex of each regex rule, by rule index
+}
+
+impl OverlapFinder {
+    fn new(rules: &[SubstitutionRule], engine: &MatchEngine) -> Self {
+        let literals = engine.literals.as_ref().map(|(_, indices)| {
+            let automaton = AhoCorasick::new(indices.iter().map(|index| &rules[*index].pattern))
+                .expect("Invalid literal automaton");
+            (automaton, indices.clone())
+        });
+        let regexes = engine.regex_rules().iter()
+            .map(|index| (*index, Regex::new(&rules[*index].matching_pattern()).expect("Invalid rule regex")))
+            .collect();
+        Self { literals, regexes }
+    }
+
+    /// Lists the matches of a content on the boundary of their rule, by start
+    fn matches(&self, rules: &[SubstitutionRule], content: &str) -> Vec<RuleMatch> {
+        let mut matches: Vec<RuleMatch> = Vec::new();
+        if let Some((automaton, indices)) = &self.literals {
+            matches.extend(automaton.find_overlapping_iter(content).map(|m| (indices[m.pattern().as_usize()], m.range())));
+        }
+        for (index, regex) in &self.regexes {
+            matches.extend(regex.find_iter(content).map(|m| (*index, m.range())));
+        }
+        matches.retain(|(index, range)| {
+            !range.is_empty() && rules[*index].options.boundary.accepts(content, range.start, range.end)
+        });
+        matches.sort_by_key(|(_, range)| range.start);
+        matches
     }
 }
 
 /// Manages multiple regex substitution rules and performs combined replacements
 ///
-/// Combines multiple patterns into a single regex for efficient matching and
-/// applies corresponding replacements based on the matched patterns.
+/// Matches literal values with a multi-pattern automaton and the regex patterns with a
+/// single combined regex, then applies the replacements of the matched rules.
+#[derive(Debug, Clone)]
 pub struct RegexReplacer {
-    combined_regex: Regex,
-    rules: Vec<SubstitutionRule>
+    engine: MatchEngine,
+    rules: Vec<SubstitutionRule>,
+    exhaustive: bool,  // Boundaries or priorities require checking every rule at a match start
+    scoped_engines: Arc<Mutex<HashMap<Vec<bool>, MatchEngine>>>,  // Engines of the rule subsets, by kept rules
+}
+
+/// Describes a case insensitive match left unchanged
+fn casing_warning(rule: &SubstitutionRule, matched: &str) -> String {
+    format!(
+        "\"{}\" left as is, no filter turns the value of {} into its casing",
+        matched,
+        rule.variable.as_deref().unwrap_or(&rule.pattern)
+    )
+}
+
+/// Tells whether the rules require checking every rule at a match start
+fn needs_exhaustive_matching(rules: &[SubstitutionRule]) -> bool {
+    rules.iter().any(|rule| rule.options.boundary != Boundary::None)
+        || rules.windows(2).any(|pair| pair[0].options.priority != pair[1].options.priority)
 }
 
 impl RegexReplacer {
     /// Creates a new RegexReplacer from a collection of substitution rules
-    pub fn new(mut rules: Vec<SubstitutionRule>) -> Self {
-        // Sort rules by pattern length (descending)
-        // This ensure that the longest pattern will matches first
-        rules.sort_by_key(|r| Reverse(r.pattern.len()));
+    ///
+    /// Rules are ordered by priority, exact casings before case insensitive rules,
+    /// the order of the collection breaking the ties. Rules with `normalize` are
+    /// followed by the rules matching the other normalisation forms of their pattern.
+    pub fn new(rules: Vec<SubstitutionRule>) -> Self {
+        let mut rules: Vec<SubstitutionRule> = rules.into_iter()
+            .filter(|rule| !rule.variant)
+            .flat_map(SubstitutionRule::with_normalization_variants)
+            .collect();
+        rules.sort_by_key(|r| (Reverse(r.options.priority), r.options.ignore_case));
+
+        // Regex rules are also matched one by one to find the longest match at a position
+        for rule in &mut rules {
+            rule.literal = !rule.options.ignore_case && regex::escape(&rule.pattern) == rule.pattern;
+            rule.anchored_regex = (!rule.literal).then(|| {
+                MetaRegex::new(&rule.matching_pattern()).expect("Invalid anchored regex")
+            });
+        }
+        Self {
+            engine: MatchEngine::new(&rules),
+            exhaustive: needs_exhaustive_matching(&rules),
+            rules,
+            scoped_engines: Arc::default()
+        }
+    }
+
+    /// Returns a RegexReplacer whose variable rules are refined by options
+    ///
+    /// # Arguments
+    /// * `options` - Options of the rules, by variable
+    /// * `defaults` - Options of the variable rules missing from `options`
+    ///
+    /// # Errors
+    /// * Returns error if options are given for a variable without rule
+    pub fn with_options(mut self, options: HashMap<String, RuleOptions>, defaults: RuleOptions) -> Result<Self, String> {
+        if let Some(variable) = options.keys().find(|variable| {
+            !self.rules.iter().any(|rule| rule.variable.as_ref() == Some(*variable))
+        }) {
+            return Err(format!("Rule options given for \"{}\", which is not a variable with a value", variable))
+        }
+        for rule in &mut self.rules {
+            let Some(variable) = &rule.variable else {
+                continue
+            };
+            rule.options = options.get(variable).unwrap_or(&defaults).clone();
+        }
+        Ok(Self::new(self.rules))
+    }
+
+    /// Returns the replacer templatizing the paths of the project
+    pub fn for_paths(&self) -> Cow<'_, RegexReplacer> {
+        self.subset(|rule| rule.options.scope.applies_to_paths())
+    }
 
-        // Combine all rules into a single regex with capture groups
-        let combined_pattern = rules.iter()
-            .map(|rule| format!("({})", rule.pattern.as_str()))
-            .collect::<Vec<_>>()
-            .join("|");
-        let combined_regex = Regex::new(&combined_pattern).expect("Invalid combined regex");
-        Self { combined_regex, rules }
+    /// Returns the replacer templatizing the content of a file
+    ///
+    /// # Arguments
+    /// * `rpath` - Relative path of the file in the source project
+    pub fn for_content(&self, rpath: &Path) -> Cow<'_, RegexReplacer> {
+        self.subset(|rule| rule.options.scope.applies_to_content(rpath))
+    }
+
+    /// Returns a replacer restricted to the rules kept by a predicate
+    ///
+    /// The engine of every subset is built once and shared by the clones.
+    fn subset(&self, keep: impl Fn(&SubstitutionRule) -> bool) -> Cow<'_, RegexReplacer> {
+        let kept: Vec<bool> = self.rules.iter().map(keep).collect();
+        if kept.iter().all(|kept| *kept) {
+            return Cow::Borrowed(self)
+        }
+        let rules: Vec<SubstitutionRule> = self.rules.iter()
+            .zip(&kept)
+            .filter(|(_, kept)| **kept)
+            .map(|(rule, _)| rule.clone())
+            .collect();
+        let engine = self.scoped_engines.lock()
+            .expect("Poisoned engine cache")
+            .entry(kept)
+            .or_insert_with(|| MatchEngine::new(&rules))
+            .clone();
+        Cow::Owned(Self {
+            engine,
+            exhaustive: needs_exhaustive_matching(&rules),
+            rules,
+            scoped_engines: self.scoped_engines.clone()
+        })
+    }
+
+    /// Returns a RegexReplacer holding additional rules
+    ///
+    /// The additional rules win over the existing ones when everything else is equal.
+    pub fn with_rules(self, mut rules: Vec<SubstitutionRule>) -> Self {
+        rules.extend(self.rules);
+        Self::new(rules)
     }
 
     /// Applies all substitution rules to the provided content
@@ -55,21 +525,357 @@ impl RegexReplacer {
     /// * String with all matching patterns replaced according to the rules
     ///
     /// # Behavior
-    /// * Matches all patterns simultaneously using a combined regex
+    /// * Matches all patterns simultaneously, literal values with a multi-pattern
+    ///   automaton and regex patterns with a combined regex
+    /// * Overlaps are resolved by taking the leftmost match, and among the matches
+    ///   starting there the one of highest priority, then the longest one, then a
+    ///   literal one, then the first rule in the order of `new`
     /// * Each pattern match is processed exactly once
     /// * Rules are applied independently and do not cascade:
     ///   - If rule1 replaces "a" with "b" and rule2 replaces "b" with "c",
     ///   - An input "a" will become "b", not "c"
     /// * Non-matching sections remain unchanged
+    /// * Matches off the boundary of their rule are ignored
+    /// * Case insensitive matches whose casing no filter reproduces remain unchanged
     pub fn replace(&self, content: &str) -> String {
-        self.combined_regex.replace_all(content, |caps: &regex::Captures| {
-            for (i, rule) in self.rules.iter().enumerate() {
-                if let Some(m) = caps.get(i + 1) {
-                    // Apply the specific replacement for this pattern
-                    return rule.pattern.replace(m.as_str(), &rule.replacement);
+        self.replace_reporting(content, &mut Vec::new())
+    }
+
+    /// Applies all substitution rules to the provided content, like `replace`
+    ///
+    /// # Arguments
+    /// * `content` - Input string to process
+    /// * `warnings` - Receives the case insensitive matches left unchanged
+    pub fn replace_reporting(&self, content: &str, warnings: &mut Vec<String>) -> String {
+        self.replace_window(content, 0, content.len(), warnings).0
+    }
+
+    /// Tells whether a content may hold a match, a fast pre-filter of `replace`
+    ///
+    /// # Returns
+    /// * `false` guarantees that `replace` leaves the content unchanged, `true` only that
+    ///   some rule matches, maybe off its boundary, so the content may still be unchanged
+    pub fn may_match(&self, content: &str) -> bool {
+        self.engine.is_match(content)
+    }
+
+    /// Returns the maximum length in bytes of a match of any rule
+    ///
+    /// # Returns
+    /// * The length, `None` if a regex rule has matches of unbounded length
+    pub fn max_match_len(&self) -> Option<usize> {
+        self.rules.iter().try_fold(0, |max, rule| Some(max.max(rule.max_match_len()?)))
+    }
+
+    /// Applies the substitution rules to a window of a content, like `replace`
+    ///
+    /// Lets a content be replaced piece by piece: the text before `from` was already
+    /// replaced and only serves as context of the boundaries.
+    ///
+    /// # Arguments
+    /// * `content` - Content holding the window
+    /// * `from` - Start of the window
+    /// * `limit` - Position from which no match may start
+    /// * `warnings` - Receives the case insensitive matches left unchanged
+    ///
+    /// # Returns
+    /// * The replaced window, up to the stop position
+    /// * The stop position: `limit`, or the end of the last match if it crosses `limit`
+    pub fn replace_window(&self, content: &str, from: usize, limit: usize, warnings: &mut Vec<String>) -> (String, usize) {
+        let mut result = String::with_capacity(limit - from);
+        let (mut last, mut position) = (from, from);
+
+        // The next match start of each engine is kept until the scan goes past it
+        let mut next_literal: Option<Option<RuleMatch>> = None;
+        let mut next_pattern: Option<Option<usize>> = None;
+        loop {
+            if next_literal.as_ref().is_none_or(|next| next.as_ref().is_some_and(|(_, range)| range.start < position)) {
+                next_literal = Some(self.engine.find_literal(content, position));
+            }
+            if next_pattern.is_none_or(|next| next.is_some_and(|start| start < position)) {
+                next_pattern = Some(self.engine.find_regex(content, position));
+            }
+            let literal = next_literal.clone().flatten();
+            let start = match (literal.as_ref().map(|(_, range)| range.start), next_pattern.flatten()) {
+                (Some(a), Some(b)) => a.min(b),
+                (a, b) => match a.or(b) {
+                    Some(start) => start,
+                    None => break
+                }
+            };
+            if start >= limit {
+                break
+            }
+
+            match self.best_match_at(content, start, literal.filter(|(_, range)| range.start == start)) {
+                Some((index, end)) => {
+                    let (rule, matched) = (&self.rules[index], &content[start..end]);
+                    result.push_str(&content[last..start]);
+                    match rule.replacement_for(matched) {
+                        Some(replacement) => result.push_str(&replacement),
+                        None => {
+                            warnings.push(casing_warning(rule, matched));
+                            result.push_str(matched);
+                        }
+                    }
+                    (last, position) = (end, end);
+                },
+                None => match content[start..].chars().next() {
+                    Some(c) => position = start + c.len_utf8(),
+                    None => break
+                }
+            }
+        }
+        let stop = last.max(limit);
+        result.push_str(&content[last..stop]);
+        (result, stop)
+    }
+
+    /// Picks the rule whose match starting at `start` wins
+    ///
+    /// # Arguments
+    /// * `literal` - Longest literal match at `start` found by the automaton, if any
+    ///
+    /// # Returns
+    /// * The index of the winning rule and the end of its match, `None` if no rule
+    ///   matches there on its boundary
+    fn best_match_at(&self, content: &str, start: usize, literal: Option<RuleMatch>) -> Option<(usize, usize)> {
+        let candidates: Box<dyn Iterator<Item = usize>> = match self.exhaustive {
+            true => Box::new(0..self.rules.len()),
+            false => Box::new(literal.map(|(index, _)| index).into_iter().chain(self.engine.regex_rules().iter().copied()))
+        };
+        candidates
+            .filter_map(|index| Some((index, self.rules[index].match_at(content, start)?)))
+            .filter(|(_, end)| *end > start)
+            .max_by_key(|(index, end)| {
+                let rule = &self.rules[*index];
+                (rule.options.priority, *end, rule.literal, Reverse(*index))
+            })
+    }
+
+    /// Lists the pairs of rules whose matches overlap in a content
+    ///
+    /// Only one rule of each pair is applied wherever they overlap, which may hide
+    /// values sharing a prefix or containing each other.
+    ///
+    /// # Returns
+    /// * Pairs of rule names, variables or patterns, each pair sorted
+    pub fn overlapping_rules(&self, content: &str) -> BTreeSet<(String, String)> {
+        let finder = self.engine.overlaps.get_or_init(|| OverlapFinder::new(&self.rules, &self.engine));
+        let mut pairs = BTreeSet::new();
+        let mut active: Vec<RuleMatch> = Vec::new();
+        for (index, range) in finder.matches(&self.rules, content) {
+            active.retain(|(_, active_range)| active_range.end > range.start);
+            for (active_index, _) in active.iter().filter(|(active_index, _)| *active_index != index) {
+                let (a, b) = (self.rules[*active_index].name(), self.rules[index].name());
+                if a != b {
+                    pairs.insert(if a <= b { (a, b) } else { (b, a) });
                 }
             }
-            caps[0].to_string()
-        }).into_owned()
+            active.push((index, range));
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoped(include: &[&str], exclude: &[&str], target: RuleTarget) -> RuleOptions {
+        let globs = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        RuleOptions {
+            scope: RuleScope::new(&globs(include), &globs(exclude), target).unwrap(),
+            boundary: Boundary::None,
+            ignore_case: false,
+            priority: 0,
+            normalize: false
+        }
+    }
+
+    fn scoped_replacer() -> RegexReplacer {
+        let options = HashMap::from([
+            ("api".to_string(), scoped(&["*.toml"], &[], RuleTarget::Both)),
+            ("name".to_string(), scoped(&[], &["*.md"], RuleTarget::Contents)),
+        ]);
+        RegexReplacer::new(vec![
+            SubstitutionRule::new("api".to_string(), "api".to_string()),
+            SubstitutionRule::new("acme".to_string(), "name".to_string()),
+        ]).with_options(options, RuleOptions::default()).unwrap()
+    }
+
+    fn bounded_replacer(boundary: Boundary) -> RegexReplacer {
+        let defaults = RuleOptions { boundary, ..RuleOptions::default() };
+        RegexReplacer::new(vec![
+            SubstitutionRule::new("app".to_string(), "app".to_string()),
+            SubstitutionRule::new("App".to_string(), "app_class".to_string()),
+            SubstitutionRule::raw("ppy".to_string(), "<ppy>".to_string()),
+        ]).with_options(HashMap::new(), defaults).unwrap()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_scoped_contents() {
+        let replacer = scoped_replacer();
+
+        assert_eq!(
+            replacer.for_content(Path::new("config/app.toml")).replace("acme api"),
+            "{{cookiecutter.name}} {{cookiecutter.api}}"
+        );
+        assert_eq!(replacer.for_content(Path::new("src/main.rs")).replace("acme api"), "{{cookiecutter.name}} api");
+        assert_eq!(replacer.for_content(Path::new("README.md")).replace("acme api"), "acme api");
+    }
+
+    #[test]
+    fn test_scoped_paths() {
+        let replacer = scoped_replacer();
+
+        assert_eq!(replacer.for_paths().replace("acme/api/main.rs"), "acme/{{cookiecutter.api}}/main.rs");
+        assert!(matches!(replacer.for_content(Path::new("a.rs")), Cow::Owned(_)));
+        assert!(matches!(RegexReplacer::new(vec![]).for_paths(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_unknown_scoped_variable() {
+        let options = HashMap::from([("other".to_string(), RuleOptions::default())]);
+        assert!(RegexReplacer::new(vec![]).with_options(options, RuleOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_word_boundary() {
+        let replacer = bounded_replacer(Boundary::Word);
+
+        assert_eq!(replacer.replace("app, happy, apps"), "{{cookiecutter.app}}, ha<ppy>, apps");
+        assert_eq!(replacer.replace("app_config"), "app_config");
+    }
+
+    #[test]
+    fn test_identifier_boundary() {
+        let replacer = bounded_replacer(Boundary::Identifier);
+
+        assert_eq!(
+            replacer.replace("app_config my-app AppConfig myApp happy"),
+            "{{cookiecutter.app}}_config my-{{cookiecutter.app}} {{cookiecutter.app_class}}Config \
+            my{{cookiecutter.app_class}} ha<ppy>"
+        );
+        assert_eq!(replacer.replace("HTTPApp Apple"), "HTTP{{cookiecutter.app_class}} Apple");
+    }
+
+    #[test]
+    fn test_case_preserving_output() {
+        let options = HashMap::from([
+            ("name".to_string(), RuleOptions { ignore_case: true, ..RuleOptions::default() }),
+        ]);
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("my project".to_string(), "name".to_string()),
+            SubstitutionRule::new("MY PROJECT".to_string(), "title".to_string()),
+        ]).with_options(options, RuleOptions::default()).unwrap();
+        let mut warnings = Vec::new();
+
+        assert_eq!(
+            replacer.replace_reporting("my project, My Project, My project, MY PROJECT, mY pRoJeCt", &mut warnings),
+            "{{cookiecutter.name}}, {{cookiecutter.name|title}}, {{cookiecutter.name|capitalize}}, \
+            {{cookiecutter.title}}, mY pRoJeCt"
+        );
+        assert_eq!(warnings, vec!["\"mY pRoJeCt\" left as is, no filter turns the value of name into its casing"]);
+    }
+
+    #[test]
+    fn test_literal_and_regex_rules() {
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("Acme".to_string(), "name".to_string()),
+            SubstitutionRule::new("AcmeCloud".to_string(), "product".to_string()),
+            SubstitutionRule::new(r"v\d+\.\d+".to_string(), "version".to_string()),
+            SubstitutionRule::new("Acme v1".to_string(), "release".to_string()),
+        ]);
+
+        assert_eq!(
+            replacer.replace("AcmeCloud Acme v1.2 v3.4 Acme"),
+            "{{cookiecutter.product}} {{cookiecutter.release}}.2 {{cookiecutter.version}} {{cookiecutter.name}}"
+        );
+        assert_eq!(RegexReplacer::new(vec![]).replace("unchanged"), "unchanged");
+    }
+
+    #[test]
+    fn test_regex_rules_see_the_preceding_text() {
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new(r"\bappy".to_string(), "a".to_string()),
+            SubstitutionRule::new("ap+".to_string(), "b".to_string()),
+        ]);
+
+        assert_eq!(replacer.replace("happy appy"), "h{{cookiecutter.b}}y {{cookiecutter.a}}");
+    }
+
+    #[test]
+    fn test_may_match() {
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("Acme".to_string(), "name".to_string()),
+            SubstitutionRule::new(r"v\d+\.\d+".to_string(), "version".to_string()),
+        ]);
+
+        assert!(replacer.may_match("by Acme"));
+        assert!(replacer.may_match("release v1.2"));
+        assert!(!replacer.may_match("nothing to see, acme v1"));
+        assert!(!RegexReplacer::new(vec![]).may_match("unchanged"));
+    }
+
+    #[test]
+    fn test_priority_and_longest_match() {
+        let rules = || vec![
+            SubstitutionRule::new("Acme".to_string(), "name".to_string()),
+            SubstitutionRule::new("AcmeCloud".to_string(), "product".to_string()),
+            SubstitutionRule::new("[A-Z][a-z]+Cloud(?:Beta)?".to_string(), "cloud".to_string()),
+        ];
+        assert_eq!(
+            RegexReplacer::new(rules()).replace("AcmeCloudBeta AcmeCloud"),
+            "{{cookiecutter.cloud}} {{cookiecutter.product}}"
+        );
+
+        let options = HashMap::from([
+            ("name".to_string(), RuleOptions { priority: 1, ..RuleOptions::default() }),
+        ]);
+        let replacer = RegexReplacer::new(rules()).with_options(options, RuleOptions::default()).unwrap();
+        assert_eq!(replacer.replace("AcmeCloud"), "{{cookiecutter.name}}Cloud");
+    }
+
+    #[test]
+    fn test_overlapping_rules() {
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("Acme".to_string(), "name".to_string()),
+            SubstitutionRule::new("AcmeCloud".to_string(), "product".to_string()),
+            SubstitutionRule::new("Cloud Platform".to_string(), "platform".to_string()),
+            SubstitutionRule::new(r"\d+\.\d+".to_string(), "version".to_string()),
+        ]);
+
+        assert_eq!(replacer.overlapping_rules("AcmeCloud Platform 1.2, Acme 3.4"), BTreeSet::from([
+            ("name".to_string(), "product".to_string()),
+            ("platform".to_string(), "product".to_string()),
+        ]));
+        assert!(replacer.overlapping_rules("Acme and Cloud Platform").is_empty());
+    }
+
+    #[test]
+    fn test_normalization_equivalence() {
+        let normalized = RuleOptions { normalize: true, ..RuleOptions::default() };
+        let replacer = RegexReplacer::new(vec![
+            SubstitutionRule::new("Zu\u{308}rich".to_string(), "city".to_string()),
+            SubstitutionRule::new("Café".to_string(), "name".to_string()),
+        ]).with_options(HashMap::from([("city".to_string(), normalized)]), RuleOptions::default()).unwrap();
+
+        assert_eq!(
+            replacer.replace("Zürich Zu\u{308}rich Café Cafe\u{301}"),
+            "{{cookiecutter.city}} {{cookiecutter.city}} {{cookiecutter.name}} Cafe\u{301}"
+        );
+        assert_eq!(replacer.for_paths().replace("Zürich/Zu\u{308}rich.txt"), "{{cookiecutter.city}}/{{cookiecutter.city}}.txt");
+        assert!(replacer.overlapping_rules("Zürich Zu\u{308}rich").is_empty());
+
+        // Variants are built again rather than piled up
+        let rules = replacer.rules.len();
+        assert_eq!(replacer.with_rules(Vec::new()).rules.len(), rules);
+    }
+
+    #[test]
+    fn test_jinja_filters() {
+        assert_eq!(jinja_title("hello big-world (again)"), "Hello Big-World (Again)");
+        assert_eq!(jinja_capitalize("hELLO World"), "Hello world");
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{Map, Value};
+use toml_edit::DocumentMut;
+
+use super::snapshot::read_project;
+
+/// Common language keywords and project words that are never proposed as variables
+const COMMON_TOKENS: [&str; 48] = [
+    "self", "this", "super", "crate", "impl", "struct", "enum", "trait", "class", "interface",
+    "function", "return", "import", "export", "from", "const", "static", "public", "private",
+    "protected", "void", "string", "bool", "true", "false", "none", "null", "else", "elif",
+    "while", "match", "async", "await", "main", "test", "tests", "index", "readme", "license",
+    "config", "package", "module", "name", "version", "description", "author", "default", "type"
+];
+
+/// A variable proposed for the cookiecutter.json of the analysed project
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub variable: String,  // The proposed cookiecutter variable name
+    pub value: String,  // The literal value found in the project
+    pub origin: String,  // Where the value was found (manifest, git config, tokens)
+    pub occurrences: usize,  // How many times the value appears in paths and contents
+}
+
+/// Collects candidates while keeping values and variable names unique
+#[derive(Default)]
+struct CandidateList {
+    candidates: Vec<Candidate>
+}
+
+impl CandidateList {
+    /// Adds a candidate unless its value is already proposed, renaming clashing variables
+    fn push(&mut self, variable: &str, value: &str, origin: &str) {
+        let value = value.trim();
+        if value.is_empty() || self.candidates.iter().any(|c| c.value == value) {
+            return
+        }
+
+        let mut name = variable.to_string();
+        let mut suffix = 2;
+        while self.candidates.iter().any(|c| c.variable == name) {
+            name = format!("{}_{}", variable, suffix);
+            suffix += 1;
+        }
+
+        self.candidates.push(Candidate {
+            variable: name,
+            value: value.to_string(),
+            origin: origin.to_string(),
+            occurrences: 0
+        });
+    }
+
+    /// Adds the name and email of an author string such as `Jane Doe <jane@example.com>`
+    fn push_author(&mut self, author: &str, origin: &str) {
+        let (name, email) = split_author(author);
+        self.push("author_name", &name, origin);
+        if let Some(email) = email {
+            self.push("author_email", &email, origin);
+        }
+    }
+}
+
+/// Splits an author string into its name and optional email
+fn split_author(author: &str) -> (String, Option<String>) {
+    let re = Regex::new(r"^\s*([^<(]*?)\s*(?:<([^>]+)>)?\s*(?:\(.*\))?\s*$").expect("Invalid author regex");
+    match re.captures(author) {
+        Some(caps) => (
+            caps[1].to_string(),
+            caps.get(2).map(|m| m.as_str().to_string())
+        ),
+        None => (author.to_string(), None)
+    }
+}
+
+/// Reads candidates from a `Cargo.toml` manifest
+fn read_cargo_toml(content: &str, list: &mut CandidateList) -> Result<()> {
+    let doc: DocumentMut = content.parse()?;
+    let package = match doc.get("package") {
+        Some(package) => package,
+        None => return Ok(())
+    };
+    if let Some(name) = package.get("name").and_then(|n| n.as_str()) {
+        list.push("project_name", name, "Cargo.toml");
+    }
+    if let Some(description) = package.get("description").and_then(|d| d.as_str()) {
+        list.push("project_description", description, "Cargo.toml");
+    }
+    let first_author = package.get("authors")
+        .and_then(|a| a.as_array())
+        .and_then(|a| a.get(0))
+        .and_then(|a| a.as_str());
+    if let Some(author) = first_author {
+        list.push_author(author, "Cargo.toml");
+    }
+    Ok(())
+}
+
+/// Reads candidates from a `package.json` manifest
+fn read_package_json(content: &str, list: &mut CandidateList) -> Result<()> {
+    let doc: Value = serde_json::from_str(content)?;
+    if let Some(name) = doc["name"].as_str() {
+        list.push("project_name", name, "package.json");
+    }
+    if let Some(description) = doc["description"].as_str() {
+        list.push("project_description", description, "package.json");
+    }
+    match &doc["author"] {
+        Value::String(author) => list.push_author(author, "package.json"),
+        Value::Object(author) => {
+            if let Some(name) = author.get("name").and_then(|n| n.as_str()) {
+                list.push("author_name", name, "package.json");
+            }
+            if let Some(email) = author.get("email").and_then(|e| e.as_str()) {
+                list.push("author_email", email, "package.json");
+            }
+        },
+        _ => ()
+    }
+    Ok(())
+}
+
+/// Reads candidates from a `pyproject.toml` manifest, PEP 621 or poetry flavoured
+fn read_pyproject_toml(content: &str, list: &mut CandidateList) -> Result<()> {
+    let doc: DocumentMut = content.parse()?;
+    let tables = [
+        (doc.get("project"), "pyproject.toml"),
+        (doc.get("tool").and_then(|t| t.get("poetry")), "pyproject.toml (poetry)")
+    ];
+    for (table, origin) in tables {
+        let table = match table {
+            Some(table) => table,
+            None => continue
+        };
+        if let Some(name) = table.get("name").and_then(|n| n.as_str()) {
+            list.push("project_name", name, origin);
+        }
+        if let Some(description) = table.get("description").and_then(|d| d.as_str()) {
+            list.push("project_description", description, origin);
+        }
+        match table.get("authors").and_then(|a| a.as_array()).and_then(|a| a.get(0)) {
+            Some(author) if author.is_str() => list.push_author(author.as_str().unwrap_or_default(), origin),
+            Some(author) => {
+                if let Some(author) = author.as_inline_table() {
+                    if let Some(name) = author.get("name").and_then(|n| n.as_str()) {
+                        list.push("author_name", name, origin);
+                    }
+                    if let Some(email) = author.get("email").and_then(|e| e.as_str()) {
+                        list.push("author_email", email, origin);
+                    }
+                }
+            },
+            None => ()
+        }
+    }
+    Ok(())
+}
+
+/// Reads candidates from a `go.mod` manifest
+fn read_go_mod(content: &str, list: &mut CandidateList) {
+    let re = Regex::new(r"(?m)^module\s+(\S+)").expect("Invalid go.mod regex");
+    if let Some(caps) = re.captures(content) {
+        let module = &caps[1];
+        if let Some(name) = module.rsplit('/').next() {
+            list.push("project_name", name, "go.mod");
+        }
+        list.push("module_path", module, "go.mod");
+    }
+}
+
+/// Returns the text of the first `<tag>` element of an XML document
+fn xml_tag(content: &str, tag: &str) -> Option<String> {
+    let re = Regex::new(&format!(r"<{0}>\s*([^<]+?)\s*</{0}>", tag)).expect("Invalid xml regex");
+    re.captures(content).map(|caps| caps[1].to_string())
+}
+
+/// Reads candidates from a maven `pom.xml`, ignoring parent and dependency coordinates
+fn read_pom_xml(content: &str, list: &mut CandidateList) {
+    let nested = Regex::new(r"(?s)<(parent|dependencies|dependencyManagement|build|plugins|profiles)>.*?</(parent|dependencies|dependencyManagement|build|plugins|profiles)>")
+        .expect("Invalid pom regex");
+    let content = nested.replace_all(content, "");
+    if let Some(artifact) = xml_tag(&content, "artifactId") {
+        list.push("project_name", &artifact, "pom.xml");
+    }
+    if let Some(group) = xml_tag(&content, "groupId") {
+        list.push("group_id", &group, "pom.xml");
+    }
+    if let Some(description) = xml_tag(&content, "description") {
+        list.push("project_description", &description, "pom.xml");
+    }
+}
+
+/// Reads candidates from a `.csproj` project file
+fn read_csproj(content: &str, file_stem: &str, origin: &str, list: &mut CandidateList) {
+    let project_name = xml_tag(content, "AssemblyName").unwrap_or_else(|| file_stem.to_string());
+    list.push("project_name", &project_name, origin);
+    if let Some(namespace) = xml_tag(content, "RootNamespace") {
+        list.push("root_namespace", &namespace, origin);
+    }
+    if let Some(authors) = xml_tag(content, "Authors") {
+        list.push("author_name", &authors, origin);
+    }
+    if let Some(company) = xml_tag(content, "Company") {
+        list.push("company_name", &company, origin);
+    }
+}
+
+/// Reads the `[user]` section of the project git config, falling back to the last commit author
+fn read_git_author(root: &Path, list: &mut CandidateList) {
+    if let Ok(config) = fs::read_to_string(root.join(".git").join("config")) {
+        let mut in_user = false;
+        for line in config.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_user = line == "[user]";
+            } else if let (true, Some((key, value))) = (in_user, line.split_once('=')) {
+                match key.trim() {
+                    "name" => list.push("author_name", value, "git config"),
+                    "email" => list.push("author_email", value, "git config"),
+                    _ => ()
+                }
+            }
+        }
+    }
+
+    if !root.join(".git").exists() {
+        return
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["log", "-1", "--format=%an%n%ae"])
+        .output();
+    if let Ok(output) = output {
+        if output.status.success() {
+            let author = String::from_utf8_lossy(&output.stdout);
+            let mut lines = author.lines();
+            if let Some(name) = lines.next() {
+                list.push("author_name", name, "git log");
+            }
+            if let Some(email) = lines.next() {
+                list.push("author_email", email, "git log");
+            }
+        }
+    }
+}
+
+/// Converts a value into a snake_case variable name
+///
+/// Camel case humps and every non alphanumeric run become a single `_`,
+/// and a `var_` prefix is added when the name would not start with a letter.
+pub fn to_variable_name(value: &str) -> String {
+    let mut name = String::new();
+    let mut previous_lower = false;
+    for c in value.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() && previous_lower {
+                name.push('_');
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            name.extend(c.to_lowercase());
+        } else {
+            previous_lower = false;
+            if !name.is_empty() && !name.ends_with('_') {
+                name.push('_');
+            }
+        }
+    }
+    let name = name.trim_end_matches('_').to_string();
+    match name.chars().next() {
+        Some(c) if c.is_alphabetic() => name,
+        _ => format!("var_{}", name)
+    }
+}
+
+/// Reader adding the values of a manifest to the candidates
+type ManifestReader = fn(&str, &mut CandidateList) -> Result<()>;
+
+/// Analyses a project and proposes cookiecutter variables with their occurrence counts
+///
+/// # Arguments
+/// * `path` - Root path of the project to analyse
+/// * `excluded_items` - List of directory or file names that are not scanned
+/// * `max_tokens` - Maximum number of high-frequency identifier tokens proposed
+/// * `warnings` - Receives the manifests skipped as they cannot be parsed
+///
+/// # Returns
+/// * `Result<Vec<Candidate>>` - Candidates in proposal order: manifests, git, tokens
+///
+/// # Behavior
+/// * Manifests (Cargo.toml, package.json, pyproject.toml, go.mod, pom.xml, *.csproj)
+///   are read at the project root, `.csproj` files anywhere in the project
+/// * Identifier tokens are only proposed when they appear both in a file or
+///   directory name and in file contents
+/// * Occurrences count every literal appearance in relative paths and text contents
+pub fn suggest_variables(
+    path: &str,
+    excluded_items: &[String],
+    max_tokens: usize,
+    warnings: &mut Vec<String>
+) -> Result<Vec<Candidate>> {
+    let root = Path::new(path);
+    let snapshot = read_project(path, excluded_items)?;
+    let (paths, contents) = (&snapshot.paths, &snapshot.contents);
+
+    let mut list = CandidateList::default();
+    let manifests: [(&str, ManifestReader); 3] =
+        [("Cargo.toml", read_cargo_toml), ("package.json", read_package_json), ("pyproject.toml", read_pyproject_toml)];
+    for (name, read) in manifests {
+        if let Some(content) = snapshot.content(name) {
+            if let Err(err) = read(content, &mut list) {
+                warnings.push(format!("{} skipped, it cannot be parsed: {}", name, err));
+            }
+        }
+    }
+    if let Some(content) = snapshot.content("go.mod") {
+        read_go_mod(content, &mut list);
+    }
+    if let Some(content) = snapshot.content("pom.xml") {
+        read_pom_xml(content, &mut list);
+    }
+    for (rpath, content) in contents.iter().filter(|(rpath, _)| rpath.ends_with(".csproj")) {
+        let stem = Path::new(rpath).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        read_csproj(content, &stem, rpath, &mut list);
+    }
+    read_git_author(root, &mut list);
+
+    // High-frequency identifier tokens that also name files or directories
+    let token_re = Regex::new(r"[A-Za-z][A-Za-z0-9_]{3,}").expect("Invalid token regex");
+    let mut path_tokens: HashSet<&str> = HashSet::new();
+    for rpath in paths {
+        let stems = Path::new(rpath).components().filter_map(|c| Path::new(c.as_os_str()).file_stem());
+        for stem in stems.filter_map(|s| s.to_str()) {
+            for m in token_re.find_iter(stem) {
+                path_tokens.insert(m.as_str());
+            }
+        }
+    }
+    let mut token_counts: HashMap<&str, usize> = HashMap::new();
+    for (_, content) in contents {
+        for m in token_re.find_iter(content) {
+            if path_tokens.contains(m.as_str()) {
+                *token_counts.entry(m.as_str()).or_insert(0) += 1;
+            }
+        }
+    }
+    let mut tokens: Vec<(&str, usize)> = token_counts
+        .into_iter()
+        .filter(|(token, _)| !COMMON_TOKENS.contains(&token.to_lowercase().as_str()))
+        .collect();
+    tokens.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (token, _) in tokens.into_iter().take(max_tokens) {
+        list.push(&to_variable_name(token), token, "identifier tokens");
+    }
+
+    // Count the occurrences of every proposed value
+    let mut candidates = list.candidates;
+    for candidate in candidates.iter_mut() {
+        candidate.occurrences = paths.iter()
+            .map(|rpath| rpath.matches(candidate.value.as_str()).count())
+            .chain(contents.iter().map(|(_, content)| content.matches(candidate.value.as_str()).count()))
+            .sum();
+    }
+
+    Ok(candidates)
+}
+
+/// Renders candidates as a proposed cookiecutter.json
+///
+/// Values are escaped, since cookiecutter.json values are the patterns of the rules:
+/// a version such as `1.0.0` only matches itself.
+///
+/// # Arguments
+/// * `candidates` - Candidates returned by `suggest_variables`
+///
+/// # Returns
+/// * `Result<String>` - Pretty printed JSON object, ready to be saved as cookiecutter.json
+pub fn render_suggestion(candidates: &[Candidate]) -> Result<String> {
+    let config = candidates.iter()
+        .map(|c| (c.variable.clone(), Value::String(regex::escape(&c.value))))
+        .collect::<Map<String, Value>>();
+    Ok(serde_json::to_string_pretty(&Value::Object(config))?)
+}
+
+/// Renders the occurrence counts of the candidates, one summary line per candidate
+pub fn render_occurrences(candidates: &[Candidate]) -> String {
+    let mut rendered = "Occurrences:\n".to_string();
+    for c in candidates {
+        rendered.push_str(&format!("  {:<20} {:>6}  {:?} ({})\n", c.variable, c.occurrences, c.value, c.origin));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Creates a project with the given files in a temporary directory
+    fn setup_project(files: &[(&str, &str)]) -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        for (file_path, content) in files {
+            let full_path = temp_dir.path().join(file_path);
+            fs::create_dir_all(full_path.parent().unwrap()).expect("Failed to create parent directory");
+            fs::write(full_path, content).expect("Failed to write test file");
+        }
+        temp_dir
+    }
+
+    /// Finds the candidate proposed for a variable
+    fn find<'a>(candidates: &'a [Candidate], variable: &str) -> &'a Candidate {
+        candidates.iter()
+            .find(|c| c.variable == variable)
+            .unwrap_or_else(|| panic!("Missing candidate {}", variable))
+    }
+
+    /// Tests values read from a Cargo manifest and their occurrence counts
+    #[test]
+    fn test_cargo_manifest() {
+        let project = setup_project(&[
+            ("Cargo.toml", "[package]\nname = \"widget\"\nauthors = [\"Jane Doe <jane@example.com>\"]\n"),
+            ("src/main.rs", "// widget by Jane Doe\nfn main() {}\n")
+        ]);
+
+        let candidates = suggest_variables(project.path().to_str().unwrap(), &[], 0, &mut Vec::new()).unwrap();
+
+        let name = find(&candidates, "project_name");
+        assert_eq!(name.value, "widget");
+        assert_eq!(name.occurrences, 2);
+        assert_eq!(find(&candidates, "author_name").value, "Jane Doe");
+        assert_eq!(find(&candidates, "author_email").value, "jane@example.com");
+    }
+
+    /// Tests values read from package.json, go.mod and pom.xml manifests
+    #[test]
+    fn test_other_manifests() {
+        let project = setup_project(&[
+            ("pyproject.toml", "[tool.poetry]\nname = \"pytool\"\nauthors = [\"Bob <bob@example.com>\"]\n"),
+            ("package.json", r#"{"name": "webapp", "author": {"name": "Ann", "email": "ann@example.com"}}"#),
+            ("go.mod", "module github.com/acme/gotool\n\ngo 1.21\n"),
+            ("pom.xml", "<project><parent><groupId>org.parent</groupId></parent><groupId>com.acme</groupId><artifactId>billing</artifactId></project>")
+        ]);
+
+        let candidates = suggest_variables(project.path().to_str().unwrap(), &[], 0, &mut Vec::new()).unwrap();
+
+        assert_eq!(find(&candidates, "project_name").value, "webapp");
+        assert_eq!(find(&candidates, "project_name_2").value, "pytool");
+        assert_eq!(find(&candidates, "author_name_2").value, "Bob");
+        assert_eq!(find(&candidates, "project_name_3").value, "gotool");
+        assert_eq!(find(&candidates, "module_path").value, "github.com/acme/gotool");
+        assert_eq!(find(&candidates, "project_name_4").value, "billing");
+        assert_eq!(find(&candidates, "group_id").value, "com.acme");
+        assert_eq!(find(&candidates, "author_email").value, "ann@example.com");
+    }
+
+    /// Tests that a malformed manifest is skipped with a warning
+    #[test]
+    fn test_malformed_manifest() {
+        let project = setup_project(&[
+            ("Cargo.toml", "[package\nname = \"widget\"\n"),
+            ("package.json", r#"{"name": "webapp"}"#)
+        ]);
+
+        let mut warnings = Vec::new();
+        let candidates = suggest_variables(project.path().to_str().unwrap(), &[], 0, &mut warnings).unwrap();
+
+        assert_eq!(find(&candidates, "project_name").value, "webapp");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Cargo.toml skipped"));
+    }
+
+    /// Tests that identifier tokens need to appear in paths and contents
+    #[test]
+    fn test_identifier_tokens() {
+        let project = setup_project(&[
+            ("AcmeWidget/AcmeWidget.cs", "class AcmeWidget { AcmeWidget x; Unrelated y; Unrelated z; }"),
+        ]);
+
+        let candidates = suggest_variables(project.path().to_str().unwrap(), &[], 5, &mut Vec::new()).unwrap();
+
+        let token = find(&candidates, "acme_widget");
+        assert_eq!(token.value, "AcmeWidget");
+        assert_eq!(token.occurrences, 5);
+        assert!(candidates.iter().all(|c| c.value != "Unrelated" && c.value != "class"));
+    }
+
+    /// Tests variable names derived from values
+    #[test]
+    fn test_to_variable_name() {
+        assert_eq!(to_variable_name("AcmeWidget"), "acme_widget");
+        assert_eq!(to_variable_name("acme-widget"), "acme_widget");
+        assert_eq!(to_variable_name("Acme Widget Inc."), "acme_widget_inc");
+        assert_eq!(to_variable_name("2fast"), "var_2fast");
+    }
+
+    /// Tests that the rendered suggestion is a valid cookiecutter.json of escaped values
+    #[test]
+    fn test_render_suggestion() {
+        let candidates = vec![
+            Candidate {
+                variable: "project_name".to_string(),
+                value: "widget".to_string(),
+                origin: "Cargo.toml".to_string(),
+                occurrences: 3
+            },
+            Candidate {
+                variable: "version".to_string(),
+                value: "1.0.0".to_string(),
+                origin: "Cargo.toml".to_string(),
+                occurrences: 2
+            }
+        ];
+
+        let parsed: Value = serde_json::from_str(&render_suggestion(&candidates).unwrap()).unwrap();
+        assert_eq!(parsed["project_name"], "widget");
+        assert_eq!(parsed["version"], r"1\.0\.0");
+
+        let summary = render_occurrences(&candidates);
+        assert!(summary.contains("project_name") && summary.contains('3') && summary.contains("\"1.0.0\""));
+    }
+}